@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Waker};
+
+/// Stops the collectors it was handed to with `with_cancellation`.
+///
+/// A graceful `shutdown` makes the collectors stop pulling new elements
+/// out of the deluge, while the elements already being evaluated are allowed
+/// to complete and are delivered as usual.
+/// A hard `cancel` drops all the elements being evaluated
+/// and all the elements that were evaluated, but not yet delivered.
+///
+/// Clones of a token control the same set of collectors.
+///
+/// # Examples
+///
+/// ```
+/// use deluge::*;
+///
+/// # futures::executor::block_on(async {
+/// let token = CancellationToken::new();
+///
+/// let result = (0..10)
+///     .into_deluge()
+///     .map(|x| {
+///         if x == 3 {
+///             token.shutdown();
+///         }
+///         async move { x }
+///     })
+///     .collect::<Vec<usize>>(2)
+///     .with_cancellation(&token)
+///     .await;
+///
+/// assert_eq!(result, vec![0, 1, 2, 3]);
+/// # });
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    inner: Arc<Mutex<TokenState>>,
+}
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancellation: Option<Cancellation>,
+    listeners: HashMap<u64, Waker>,
+    next_listener_id: u64,
+}
+
+/// How a collector was asked to stop
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Cancellation {
+    Shutdown,
+    Cancel,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops pulling new elements, letting the ones in flight complete
+    pub fn shutdown(&self) {
+        self.stop(Cancellation::Shutdown);
+    }
+
+    /// Drops all the elements in flight and the ones not yet delivered
+    pub fn cancel(&self) {
+        self.stop(Cancellation::Cancel);
+    }
+
+    /// True if either `shutdown` or `cancel` was called
+    pub fn is_stopped(&self) -> bool {
+        self.inner.lock().unwrap().cancellation.is_some()
+    }
+
+    pub(crate) fn listen(&self) -> Listener {
+        let mut state = self.inner.lock().unwrap();
+        let id = state.next_listener_id;
+        state.next_listener_id += 1;
+
+        Listener {
+            token: self.clone(),
+            id,
+        }
+    }
+
+    fn stop(&self, cancellation: Cancellation) {
+        let mut state = self.inner.lock().unwrap();
+        // A graceful shutdown can still be turned into a hard cancel, but not the other way around
+        if state.cancellation != Some(Cancellation::Cancel) {
+            state.cancellation = Some(cancellation);
+        }
+
+        state.listeners.drain().for_each(|(_, waker)| waker.wake());
+    }
+}
+
+/// Wakes up a single collector when its token gets stopped
+#[derive(Debug)]
+pub(crate) struct Listener {
+    token: CancellationToken,
+    id: u64,
+}
+
+impl Listener {
+    /// Returns how the collector was asked to stop, if it was.
+    /// Otherwise makes sure the collector is woken up once that happens.
+    pub(crate) fn poll(&self, cx: &mut Context<'_>) -> Option<Cancellation> {
+        let mut state = self.token.inner.lock().unwrap();
+        if state.cancellation.is_none() {
+            state.listeners.insert(self.id, cx.waker().clone());
+        }
+
+        state.cancellation
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.token.inner.lock().unwrap().listeners.remove(&self.id);
+    }
+}
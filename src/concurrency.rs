@@ -0,0 +1,405 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// Controls how many elements a collector evaluates at once.
+///
+/// Usually created implicitly from the argument passed to a collector:
+/// `None` means no limit, a number is a limit local to a single collector
+/// and a `ConcurrencyLimiter` is a limit shared with every other collector using it.
+#[derive(Clone, Debug, Default)]
+pub enum Concurrency {
+    #[default]
+    Unlimited,
+    Limited(NonZeroUsize),
+    Shared(ConcurrencyLimiter),
+}
+
+impl From<Option<usize>> for Concurrency {
+    fn from(limit: Option<usize>) -> Self {
+        match limit.and_then(NonZeroUsize::new) {
+            Some(limit) => Concurrency::Limited(limit),
+            None => Concurrency::Unlimited,
+        }
+    }
+}
+
+impl From<usize> for Concurrency {
+    fn from(limit: usize) -> Self {
+        Some(limit).into()
+    }
+}
+
+impl From<ConcurrencyLimiter> for Concurrency {
+    fn from(limiter: ConcurrencyLimiter) -> Self {
+        Concurrency::Shared(limiter)
+    }
+}
+
+impl From<&ConcurrencyLimiter> for Concurrency {
+    fn from(limiter: &ConcurrencyLimiter) -> Self {
+        Concurrency::Shared(limiter.clone())
+    }
+}
+
+impl From<AdaptiveConcurrency> for Concurrency {
+    fn from(adaptive: AdaptiveConcurrency) -> Self {
+        Concurrency::Shared(adaptive.limiter)
+    }
+}
+
+impl From<&AdaptiveConcurrency> for Concurrency {
+    fn from(adaptive: &AdaptiveConcurrency) -> Self {
+        Concurrency::Shared(adaptive.limiter.clone())
+    }
+}
+
+/// A concurrency budget that can be shared between many pipelines.
+///
+/// Each element being evaluated by a collector holds a permit from the limiter,
+/// so all the collectors using clones of the same limiter together evaluate
+/// at most `permits` elements at once.
+/// Waiting collectors are served in the order they asked for a permit.
+///
+/// The permits can also be treated as cost units with `DelugeExt::map_weighted`,
+/// where each element holds as many permits as it costs.
+///
+/// # Examples
+///
+/// ```
+/// use deluge::*;
+///
+/// # futures::executor::block_on(async {
+/// let limiter = ConcurrencyLimiter::new(5);
+///
+/// let (first, second) = futures::join!(
+///     (0..10).into_deluge().collect::<Vec<usize>>(&limiter),
+///     (0..10).into_deluge().collect::<Vec<usize>>(&limiter),
+/// );
+///
+/// assert_eq!(first.len() + second.len(), 20);
+/// # });
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConcurrencyLimiter {
+    inner: Arc<Mutex<LimiterState>>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    capacity: usize,
+    in_use: usize,
+    waiters: VecDeque<Waiter>,
+    next_waiter_id: u64,
+    // Adjusts the capacity when the limiter is adaptive
+    aimd: Option<Aimd>,
+}
+
+impl LimiterState {
+    fn available(&self) -> usize {
+        // The capacity of an adaptive limiter might drop below the permits in use
+        self.capacity.saturating_sub(self.in_use)
+    }
+}
+
+#[derive(Debug)]
+struct Waiter {
+    id: u64,
+    waker: Waker,
+}
+
+impl From<usize> for ConcurrencyLimiter {
+    fn from(permits: usize) -> Self {
+        ConcurrencyLimiter::new(permits)
+    }
+}
+
+impl From<&ConcurrencyLimiter> for ConcurrencyLimiter {
+    fn from(limiter: &ConcurrencyLimiter) -> Self {
+        limiter.clone()
+    }
+}
+
+impl ConcurrencyLimiter {
+    /// Creates a limiter allowing at most `permits` elements to be evaluated at once
+    pub fn new(permits: usize) -> Self {
+        let permits = std::cmp::max(permits, 1);
+
+        Self {
+            inner: Arc::new(Mutex::new(LimiterState {
+                capacity: permits,
+                in_use: 0,
+                waiters: VecDeque::new(),
+                next_waiter_id: 0,
+                aimd: None,
+            })),
+        }
+    }
+
+    /// The number of permits that are not currently held by any element
+    pub fn available(&self) -> usize {
+        self.inner.lock().unwrap().available()
+    }
+
+    /// Waits until `units` permits are available.
+    pub(crate) fn acquire(&self, units: usize) -> Acquire {
+        Acquire {
+            limiter: self.clone(),
+            units,
+            waiter_id: None,
+        }
+    }
+
+    /// Takes `units` permits if they are available right away
+    /// and nobody else is waiting for them.
+    pub(crate) fn try_acquire(&self, units: usize) -> Option<Permit> {
+        let mut state = self.inner.lock().unwrap();
+        let units = std::cmp::min(units, state.capacity);
+
+        if state.waiters.is_empty() && state.available() >= units {
+            state.in_use += units;
+            Some(Permit {
+                limiter: self.clone(),
+                units,
+                acquired_at: Instant::now(),
+            })
+        } else {
+            None
+        }
+    }
+
+    fn release(&self, units: usize) {
+        let mut state = self.inner.lock().unwrap();
+        state.in_use -= units;
+
+        if let Some(waiter) = state.waiters.front() {
+            waiter.waker.wake_by_ref();
+        }
+    }
+}
+
+/// Permits to evaluate an element, returned to the limiter when dropped
+#[derive(Debug)]
+pub(crate) struct Permit {
+    limiter: ConcurrencyLimiter,
+    units: usize,
+    acquired_at: Instant,
+}
+
+impl Permit {
+    /// Returns the permit after its element has been evaluated,
+    /// letting an adaptive limiter learn from how that went
+    pub(crate) fn complete(self, failed: bool) {
+        let mut state = self.limiter.inner.lock().unwrap();
+        if let Some(aimd) = state.aimd.as_mut() {
+            state.capacity = aimd.record(self.acquired_at, failed);
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.release(self.units);
+    }
+}
+
+/// A future resolving to a `Permit` once enough permits become available
+pub(crate) struct Acquire {
+    limiter: ConcurrencyLimiter,
+    units: usize,
+    waiter_id: Option<u64>,
+}
+
+impl Future for Acquire {
+    type Output = Permit;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Permit> {
+        let this = &mut *self;
+        let mut state = this.limiter.inner.lock().unwrap();
+        // An element requiring more than the whole budget is admitted on its own
+        let units = std::cmp::min(this.units, state.capacity);
+
+        let first_in_line = match this.waiter_id {
+            Some(id) => state.waiters.front().map(|waiter| waiter.id) == Some(id),
+            None => state.waiters.is_empty(),
+        };
+
+        if first_in_line && state.available() >= units {
+            state.in_use += units;
+            if this.waiter_id.take().is_some() {
+                state.waiters.pop_front();
+            }
+            // Whoever is next might be satisfied with what is left
+            if let Some(waiter) = state.waiters.front() {
+                waiter.waker.wake_by_ref();
+            }
+
+            return Poll::Ready(Permit {
+                limiter: this.limiter.clone(),
+                units,
+                acquired_at: Instant::now(),
+            });
+        }
+
+        match this.waiter_id {
+            Some(id) => {
+                if let Some(waiter) = state.waiters.iter_mut().find(|waiter| waiter.id == id) {
+                    waiter.waker.clone_from(cx.waker());
+                }
+            }
+            None => {
+                let id = state.next_waiter_id;
+                state.next_waiter_id += 1;
+                state.waiters.push_back(Waiter {
+                    id,
+                    waker: cx.waker().clone(),
+                });
+                this.waiter_id = Some(id);
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        if let Some(id) = self.waiter_id {
+            let mut state = self.limiter.inner.lock().unwrap();
+            state.waiters.retain(|waiter| waiter.id != id);
+
+            if let Some(waiter) = state.waiters.front() {
+                waiter.waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+/// A concurrency limit that adjusts itself to the observed element latency.
+///
+/// Starts low and additively increases the limit by roughly one per limit's worth
+/// of elements completing within `target_latency`. When an element takes longer
+/// than that, or the collector was told it failed with `backoff_when`,
+/// the limit is multiplicatively decreased.
+///
+/// Like `ConcurrencyLimiter`, it can be shared between many pipelines.
+/// Keep a clone around to observe the current limit.
+///
+/// # Examples
+///
+/// ```
+/// use deluge::*;
+/// use std::time::Duration;
+///
+/// # futures::executor::block_on(async {
+/// let adaptive = AdaptiveConcurrency::new(Duration::from_secs(1)).max_limit(8);
+///
+/// let result = (0..100)
+///     .into_deluge()
+///     .map(|x| async move { if x % 10 == 0 { Err(x) } else { Ok(x) } })
+///     .collect::<Vec<Result<usize, usize>>>(&adaptive)
+///     .backoff_when(Result::is_err)
+///     .await;
+///
+/// assert_eq!(result.len(), 100);
+/// assert!(adaptive.current_limit() <= 8);
+/// # });
+/// ```
+#[derive(Clone, Debug)]
+pub struct AdaptiveConcurrency {
+    limiter: ConcurrencyLimiter,
+}
+
+#[derive(Debug)]
+struct Aimd {
+    limit: f64,
+    min_limit: usize,
+    max_limit: usize,
+    target_latency: Duration,
+    backoff_ratio: f64,
+    last_backoff: Option<Instant>,
+}
+
+impl Aimd {
+    /// Adjusts the limit after an element which started at `started_at` completes
+    fn record(&mut self, started_at: Instant, failed: bool) -> usize {
+        let now = Instant::now();
+
+        if failed || now - started_at > self.target_latency {
+            // Elements started before the last backoff were already running
+            // under the higher limit, so they shouldn't lower it again
+            if self.last_backoff.is_none_or(|last| started_at >= last) {
+                self.limit *= self.backoff_ratio;
+                self.last_backoff = Some(now);
+            }
+        } else {
+            self.limit += 1.0 / self.limit;
+        }
+
+        self.limit = self
+            .limit
+            .clamp(self.min_limit as f64, self.max_limit as f64);
+        self.limit as usize
+    }
+}
+
+impl AdaptiveConcurrency {
+    /// Creates a limit which starts at a single element
+    /// and grows while elements complete within `target_latency`
+    pub fn new(target_latency: Duration) -> Self {
+        let limiter = ConcurrencyLimiter::new(1);
+        limiter.inner.lock().unwrap().aimd = Some(Aimd {
+            limit: 1.0,
+            min_limit: 1,
+            max_limit: usize::MAX,
+            target_latency,
+            backoff_ratio: 0.5,
+            last_backoff: None,
+        });
+
+        Self { limiter }
+    }
+
+    /// Sets the limit the collectors start with
+    pub fn initial_limit(self, limit: usize) -> Self {
+        self.update(|aimd| aimd.limit = limit as f64)
+    }
+
+    /// The limit never goes below `limit`, defaults to one
+    pub fn min_limit(self, limit: usize) -> Self {
+        self.update(|aimd| aimd.min_limit = std::cmp::max(limit, 1))
+    }
+
+    /// The limit never goes above `limit`, unbounded by default
+    pub fn max_limit(self, limit: usize) -> Self {
+        self.update(|aimd| aimd.max_limit = std::cmp::max(limit, 1))
+    }
+
+    /// Sets how much the limit is multiplied by on a backoff, defaults to a half
+    pub fn backoff_ratio(self, ratio: f64) -> Self {
+        self.update(|aimd| aimd.backoff_ratio = ratio.clamp(0.0, 1.0))
+    }
+
+    /// The number of elements that can currently be evaluated at once
+    pub fn current_limit(&self) -> usize {
+        self.limiter.inner.lock().unwrap().capacity
+    }
+
+    fn update(self, f: impl FnOnce(&mut Aimd)) -> Self {
+        {
+            let mut state = self.limiter.inner.lock().unwrap();
+            let aimd = state.aimd.as_mut().unwrap();
+            f(aimd);
+            aimd.max_limit = std::cmp::max(aimd.max_limit, aimd.min_limit);
+            aimd.limit = aimd
+                .limit
+                .clamp(aimd.min_limit as f64, aimd.max_limit as f64);
+            state.capacity = aimd.limit as usize;
+        }
+        self
+    }
+}
@@ -1,4 +1,5 @@
 use std::future::Future;
+use std::task::{Context, Poll};
 
 /// A stream of unevaluated futures eventually returning an element of the stream
 ///
@@ -7,12 +8,53 @@ use std::future::Future;
 /// element was filtered out earlier in the processing chain and should be omitted.
 ///
 /// If `None` is returned from the call to `next`, the Deluge has ran out of items to provide.
-/// Calling `next` again will be unsafe and may lead to panics.
+/// Implementations should keep returning `None` from then on.
+/// The deluges created by this crate do, with adapters such as `map` relying on their input to.
+/// Wrap a custom implementation with `fuse` if it can't guarantee that on its own.
+///
+/// Neither the elements nor the futures need to be `Send`, so the collectors
+/// can evaluate `Rc` based state on a single threaded executor.
+/// That includes the parallel ones, as their workers are polled by the collector
+/// instead of being spawned onto other threads.
 pub trait Deluge {
-    type Item: Send;
+    type Item;
     type Output<'x>: Future<Output = Option<Self::Item>> + 'x
     where
         Self: 'x;
 
     fn next(&self) -> Option<Self::Output<'_>>;
+
+    /// Returns the bounds on the number of elements left in the deluge,
+    /// much like `Iterator::size_hint`.
+    ///
+    /// The lower bound counts the elements that are certain not to be filtered out,
+    /// the upper bound counts the futures `next` is yet to return.
+    /// Equal bounds mean that none of the remaining elements get filtered out.
+    ///
+    /// The hint only helps the collectors allocate up front, it is never relied on.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
+
+    /// Tells the concurrent collectors whether to ask for another future right away,
+    /// much like `Sink::poll_ready`.
+    ///
+    /// A deluge that only learns how many elements it has by evaluating the futures
+    /// it already handed out can return `Poll::Pending` until one of them gets further,
+    /// waking `cx` up then, instead of handing out a future for every element it might have.
+    /// It is only allowed to do so while some of those futures are still unresolved.
+    /// `next` keeps working either way, for the callers that don't ask.
+    fn poll_ready(&self, _cx: &mut Context<'_>) -> Poll<()> {
+        Poll::Ready(())
+    }
+
+    /// Returns how many of the futures handed out by `next` are unable to get any further
+    /// until other ones of them complete, like the `map_keyed` elements waiting for
+    /// an earlier element with the same key.
+    ///
+    /// The concurrent collectors leave them out when checking their concurrency limit,
+    /// so they can start the elements that are able to run meanwhile.
+    fn parked(&self) -> usize {
+        0
+    }
 }
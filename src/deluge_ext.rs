@@ -1,6 +1,11 @@
+use std::cmp::Ordering;
 use std::default::Default;
 use std::future::Future;
+use std::hash::Hash;
+#[cfg(feature = "async-runtime")]
+use std::time::{Duration, Instant};
 
+use crate::concurrency::{Concurrency, ConcurrencyLimiter};
 use crate::deluge::Deluge;
 use crate::ops::*;
 
@@ -34,10 +39,10 @@ pub trait DelugeExt: Deluge {
     /// assert!(!result);
     /// # });
     /// ```
-    fn all<'a, Fut, F>(self, concurrency: impl Into<Option<usize>>, f: F) -> All<'a, Self, Fut, F>
+    fn all<'a, Fut, F>(self, concurrency: impl Into<Concurrency>, f: F) -> All<'a, Self, Fut, F>
     where
-        F: Fn(Self::Item) -> Fut + Send + 'a,
-        Fut: Future<Output = bool> + Send,
+        F: Fn(Self::Item) -> Fut + 'a,
+        Fut: Future<Output = bool>,
         Self: Sized,
     {
         All::new(self, concurrency, f)
@@ -73,7 +78,7 @@ pub trait DelugeExt: Deluge {
     fn all_par<'a, Fut, F>(
         self,
         worker_count: impl Into<Option<usize>>,
-        worker_concurrency: impl Into<Option<usize>>,
+        worker_concurrency: impl Into<Concurrency>,
         f: F,
     ) -> AllPar<'a, Self, Fut, F>
     where
@@ -110,10 +115,10 @@ pub trait DelugeExt: Deluge {
     /// assert!(!result);
     /// # });
     /// ```
-    fn any<'a, Fut, F>(self, concurrency: impl Into<Option<usize>>, f: F) -> Any<'a, Self, Fut, F>
+    fn any<'a, Fut, F>(self, concurrency: impl Into<Concurrency>, f: F) -> Any<'a, Self, Fut, F>
     where
-        F: Fn(Self::Item) -> Fut + Send + 'a,
-        Fut: Future<Output = bool> + Send,
+        F: Fn(Self::Item) -> Fut + 'a,
+        Fut: Future<Output = bool>,
         Self: Sized,
     {
         Any::new(self, concurrency, f)
@@ -149,7 +154,7 @@ pub trait DelugeExt: Deluge {
     fn any_par<'a, Fut, F>(
         self,
         worker_count: impl Into<Option<usize>>,
-        worker_concurrency: impl Into<Option<usize>>,
+        worker_concurrency: impl Into<Concurrency>,
         f: F,
     ) -> AnyPar<'a, Self, Fut, F>
     where
@@ -160,7 +165,104 @@ pub trait DelugeExt: Deluge {
         AnyPar::new(self, worker_count, worker_concurrency, f)
     }
 
-    /// Chains two deluges together
+    /// Erases the type of the deluge, so pipelines built in different ways
+    /// can be stored in the same field or returned from different branches.
+    ///
+    /// Each element is boxed, trading an allocation for the flexibility.
+    /// The deluge and its futures have to be `Send`, which the compiler can only tell
+    /// for the deluges that don't borrow anything, the others can use `boxed_local`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let double = true;
+    /// let deluge: BoxDeluge<'static, usize> = if double {
+    ///     (0..4).into_deluge().map(|x| async move { x * 2 }).boxed()
+    /// } else {
+    ///     (0..4).into_deluge().boxed()
+    /// };
+    ///
+    /// let result = deluge.collect::<Vec<usize>>(None).await;
+    /// assert_eq!(vec![0, 2, 4, 6], result);
+    /// # });
+    /// ```
+    fn boxed<'a>(self) -> BoxDeluge<'a, Self::Item>
+    where
+        Self: Sized + Send + 'a,
+        for<'x> Self::Output<'x>: Send,
+    {
+        BoxDeluge::new(self)
+    }
+
+    /// Erases the type of the deluge like `boxed`,
+    /// for the deluges that can't be sent to other threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    /// use std::rc::Rc;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let offset = Rc::new(10);
+    /// let deluge: LocalBoxDeluge<'_, usize> = (0..4)
+    ///     .into_deluge()
+    ///     .map(|x| {
+    ///         let offset = offset.clone();
+    ///         async move { x + *offset }
+    ///     })
+    ///     .boxed_local();
+    ///
+    /// let result = deluge.collect::<Vec<usize>>(None).await;
+    /// assert_eq!(vec![10, 11, 12, 13], result);
+    /// # });
+    /// ```
+    fn boxed_local<'a>(self) -> LocalBoxDeluge<'a, Self::Item>
+    where
+        Self: Sized + 'a,
+    {
+        LocalBoxDeluge::new(self)
+    }
+
+    /// Catches the panics of each element, turning a panicked element into `Err(PanicInfo)`.
+    ///
+    /// To drop the panicked elements instead, or to resume the panic once the other
+    /// elements were collected, pass a `PanicPolicy` to the collector with `on_panic`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = [1, 2, 3, 4]
+    ///     .into_deluge()
+    ///     .map(|x| async move {
+    ///         if x == 2 {
+    ///             panic!("two");
+    ///         }
+    ///         x
+    ///     })
+    ///     .catch_unwind()
+    ///     .collect::<Vec<Result<usize, PanicInfo>>>(None)
+    ///     .await;
+    ///
+    /// assert_eq!(result[1].as_ref().unwrap_err().message(), Some("two"));
+    /// assert_eq!(result.into_iter().filter_map(Result::ok).collect::<Vec<_>>(), vec![1, 3, 4]);
+    /// # });
+    /// ```
+    fn catch_unwind(self) -> CatchUnwind<Self>
+    where
+        Self: Sized,
+    {
+        CatchUnwind::new(self)
+    }
+
+    /// Chains two deluges together.
+    /// They only need to have the same `Item`, not to be built the same way.
     ///
     /// # Examples
     ///
@@ -180,7 +282,7 @@ pub trait DelugeExt: Deluge {
     /// ```
     fn chain<'a, Del2>(self, deluge2: Del2) -> Chain<'a, Self, Del2>
     where
-        Del2: for<'x> Deluge<Item = Self::Item, Output<'x> = Self::Output<'x>> + 'static,
+        Del2: Deluge<Item = Self::Item>,
         Self: Sized,
     {
         Chain::new(self, deluge2)
@@ -228,21 +330,229 @@ pub trait DelugeExt: Deluge {
     /// ```
     fn map<Fut, F>(self, f: F) -> Map<Self, F>
     where
-        F: Fn(Self::Item) -> Fut + Send,
-        Fut: Future + Send,
+        F: Fn(Self::Item) -> Fut,
+        Fut: Future,
         Self: Sized,
     {
         Map::new(self, f)
     }
 
-    /// Leaves the elements for which `f` returns a promise evaluating to `true`.
+    /// Transforms each element by applying a synchronous, CPU heavy function `f` to it.
+    ///
+    /// `f` runs on the blocking thread pool of the runtime instead of the async executor,
+    /// so it doesn't stall the other futures while it works.
+    /// The concurrency limit of the collector caps how many of them run at once
+    /// and the results are returned in the order of the input elements.
+    /// Use `with_runtime(RayonRuntime::default())` to run `f` on rayon
+    /// when the `rayon` feature is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # let rt = tokio::runtime::Runtime::new().unwrap();
+    /// # rt.handle().block_on(async {
+    /// let result = ["1", "22", "333"]
+    ///     .into_deluge()
+    ///     .map_blocking(|x| x.len())
+    ///     .collect::<Vec<usize>>(2)
+    ///     .await;
+    ///
+    /// assert_eq!(vec![1, 2, 3], result);
+    /// # });
+    /// ```
+    #[cfg(feature = "async-runtime")]
+    fn map_blocking<F, Out>(self, f: F) -> MapBlocking<Self, F>
+    where
+        Self::Item: Send + 'static,
+        F: Fn(Self::Item) -> Out + Send + Sync + 'static,
+        Out: Send + 'static,
+        Self: Sized,
+    {
+        MapBlocking::new(self, f)
+    }
+
+    /// Transforms each element by applying an asynchronous function `f` to it,
+    /// making sure that elements sharing the same key are processed one after another.
+    ///
+    /// The key is computed with `key_fn` from the input element.
+    /// Elements with the same key run sequentially, in the order of the input deluge,
+    /// while elements with different keys are evaluated concurrently, up to the
+    /// concurrency limit of the collector.
+    /// An element waiting for its turn doesn't count against the limit of `collect`,
+    /// so a slow element doesn't keep the ones with other keys behind it from starting.
+    /// The parallel collectors and a shared `ConcurrencyLimiter` still count it.
+    ///
+    /// The per-key order is kept by the op rather than by a dedicated `collect_keyed`,
+    /// so it holds whichever collector the results end up in. It's also the only place
+    /// where the key can be read, as it comes from the input element before `f` runs,
+    /// while a collector only gets to see the futures returned by `f`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = [(1, 10), (2, 20), (1, 30), (2, 40)]
+    ///     .into_deluge()
+    ///     .map_keyed(|(account, _)| *account, |(account, amount)| async move {
+    ///         (account, amount * 2)
+    ///     })
+    ///     .collect::<Vec<(usize, usize)>>(None)
+    ///     .await;
+    ///
+    /// assert_eq!(vec![(1, 20), (2, 40), (1, 60), (2, 80)], result);
+    /// # });
+    /// ```
+    fn map_keyed<K, KF, Fut, F>(self, key_fn: KF, f: F) -> MapKeyed<Self, K, KF, F>
+    where
+        K: Hash + Eq + Clone,
+        KF: Fn(&Self::Item) -> K,
+        F: Fn(Self::Item) -> Fut,
+        Fut: Future,
+        Self: Sized,
+    {
+        MapKeyed::new(self, key_fn, f)
+    }
+
+    /// Transforms each element by applying a fallible asynchronous function `f` to it,
+    /// calling it again for the failed elements as described by the retry `policy`.
+    /// Resolves to the first successful result or to the last error.
+    ///
+    /// Retries are performed in place, so a retried element keeps occupying
+    /// its concurrency slot in the collector and keeps its position in the output.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use std::time::Duration;
+    ///
+    /// # let rt = tokio::runtime::Runtime::new().unwrap();
+    /// # rt.handle().block_on(async {
+    /// let calls = AtomicUsize::new(0);
+    /// let result = [1, 2, 3]
+    ///     .into_deluge()
+    ///     .map_retry(RetryPolicy::fixed(Duration::from_millis(10)), |x| {
+    ///         let call = calls.fetch_add(1, Ordering::SeqCst);
+    ///         async move {
+    ///             if call == 0 {
+    ///                 Err("flaky")
+    ///             } else {
+    ///                 Ok(x * 2)
+    ///             }
+    ///         }
+    ///     })
+    ///     .collect::<Vec<Result<usize, &str>>>(None)
+    ///     .await;
+    ///
+    /// assert_eq!(vec![Ok(2), Ok(4), Ok(6)], result);
+    /// # });
+    /// ```
+    #[cfg(feature = "async-runtime")]
+    fn map_retry<Fut, F, T, E>(self, policy: RetryPolicy, f: F) -> MapRetry<Self, F>
+    where
+        Self::Item: Clone,
+        F: Fn(Self::Item) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        Self: Sized,
+    {
+        MapRetry::new(self, policy, f)
+    }
+
+    /// Transforms each element by applying an asynchronous function `f` to it,
+    /// limiting the total cost of the elements being transformed at once.
+    ///
+    /// The cost of each element is computed with `cost_fn` from the input element
+    /// and `f` only runs once the `limiter` has that many units available.
+    /// The units are returned when `f` completes.
+    /// An element costing more than the whole budget runs alone,
+    /// so it can't stall the pipeline forever.
+    ///
+    /// Pass a number to create a budget for this pipeline only,
+    /// or a `&ConcurrencyLimiter` to share the budget with other pipelines.
+    ///
+    /// The cost is charged before `f` starts, while a collector would only get to weigh
+    /// an element once `f` has already finished, be it an upload of a kilobyte or a gigabyte.
+    /// That's why the budget is set here rather than on `collect` or `collect_par`.
+    /// Both work with it unchanged, the whole budget is shared by all the parallel workers,
+    /// and a limit passed to the collector still caps the number of elements on top of it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = [1, 1024, 2, 4096]
+    ///     .into_deluge()
+    ///     .map_weighted(2048, |size| *size, |size| async move { size / 2 })
+    ///     .collect::<Vec<usize>>(None)
+    ///     .await;
     ///
-    /// # WARNING
+    /// assert_eq!(vec![0, 512, 1, 2048], result);
+    /// # });
+    /// ```
+    fn map_weighted<Fut, CF, F>(
+        self,
+        limiter: impl Into<ConcurrencyLimiter>,
+        cost_fn: CF,
+        f: F,
+    ) -> MapWeighted<Self, CF, F>
+    where
+        CF: Fn(&Self::Item) -> usize,
+        F: Fn(Self::Item) -> Fut,
+        Fut: Future,
+        Self: Sized,
+    {
+        MapWeighted::new(self, limiter, cost_fn, f)
+    }
+
+    /// Merges deluges that are already sorted according to `cmp` into a single sorted one.
+    /// Equal elements keep the order of the deluges they came from.
     ///
-    /// Currently has [a breaking bug](https://github.com/mkawalec/deluge/issues/1).
+    /// The heads of all the deluges are evaluated concurrently,
+    /// along with at most a few more elements of each,
+    /// which can be adjusted with `with_lookahead`.
+    /// All the deluges need to have the same type, `boxed` can make that so.
     ///
     /// # Examples
     ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = vec![1, 4, 7]
+    ///     .into_deluge()
+    ///     .merge_sorted_by([vec![2, 5, 8].into_deluge(), vec![3, 6].into_deluge()], |a, b| {
+    ///         a.cmp(b)
+    ///     })
+    ///     .collect::<Vec<usize>>(None)
+    ///     .await;
+    ///
+    /// assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8], result);
+    /// # });
+    /// ```
+    fn merge_sorted_by<'a, I, F>(self, others: I, cmp: F) -> MergeSortedBy<'a, Self, F>
+    where
+        I: IntoIterator<Item = Self>,
+        F: Fn(&Self::Item, &Self::Item) -> Ordering,
+        Self: Sized + 'a,
+    {
+        MergeSortedBy::new(std::iter::once(self).chain(others).collect(), cmp)
+    }
+
+    // Leaves the elements for which `f` returns a promise evaluating to `true`.
+    //
+    // # WARNING
+    //
+    // Currently has [a breaking bug](https://github.com/mkawalec/deluge/issues/1).
+    //
+    // # Examples
+    //
     // ```
     // use deluge::*;
     //
@@ -290,8 +600,8 @@ pub trait DelugeExt: Deluge {
     /// ```
     fn filter_map<Fut, F>(self, f: F) -> FilterMap<Self, F>
     where
-        F: Fn(Self::Item) -> Fut + Send,
-        Fut: Future + Send,
+        F: Fn(Self::Item) -> Fut,
+        Fut: Future,
         Self: Sized,
     {
         FilterMap::new(self, f)
@@ -354,13 +664,13 @@ pub trait DelugeExt: Deluge {
     /// ```
     fn fold<Acc, F, Fut>(
         self,
-        concurrency: impl Into<Option<usize>>,
+        concurrency: impl Into<Concurrency>,
         acc: Acc,
         f: F,
     ) -> Fold<Self, Acc, F, Fut>
     where
-        F: FnMut(Acc, Self::Item) -> Fut + Send,
-        Fut: Future<Output = Acc> + Send,
+        F: FnMut(Acc, Self::Item) -> Fut,
+        Fut: Future<Output = Acc>,
         Self: Sized,
     {
         Fold::new(self, concurrency, acc, f)
@@ -385,11 +695,10 @@ pub trait DelugeExt: Deluge {
     /// assert_eq!(result, 4950);
     /// # });
     /// ```
-    #[cfg(feature = "async-runtime")]
     fn fold_par<'a, Acc, F, Fut>(
         self,
         worker_count: impl Into<Option<usize>>,
-        worker_concurrency: impl Into<Option<usize>>,
+        worker_concurrency: impl Into<Concurrency>,
         acc: Acc,
         f: F,
     ) -> FoldPar<'a, Self, Acc, F, Fut>
@@ -401,30 +710,88 @@ pub trait DelugeExt: Deluge {
         FoldPar::new(self, worker_count, worker_concurrency, acc, f)
     }
 
-    /// Returns the last element of the input deluge and then finishes
+    /// Makes sure `next` keeps returning `None` once the deluge is exhausted.
+    ///
+    /// The deluges created by this crate already behave this way,
+    /// `fuse` adds the same guarantee to a custom implementation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    /// use std::cell::Cell;
+    /// use std::future::{ready, Ready};
+    ///
+    /// // Alternates between providing an element and running out of them
+    /// struct Blinking(Cell<bool>);
+    ///
+    /// impl Deluge for Blinking {
+    ///     type Item = usize;
+    ///     type Output<'x> = Ready<Option<usize>>;
+    ///
+    ///     fn next(&self) -> Option<Self::Output<'_>> {
+    ///         self.0.set(!self.0.get());
+    ///         self.0.get().then(|| ready(Some(1)))
+    ///     }
+    /// }
+    ///
+    /// let fused = Blinking(Cell::new(false)).fuse();
+    /// assert!(fused.next().is_some());
+    /// assert!(fused.next().is_none());
+    /// assert!(fused.next().is_none());
+    /// ```
+    fn fuse(self) -> Fuse<Self>
+    where
+        Self: Sized,
+    {
+        Fuse::new(self)
+    }
+
+    /// Pairs up the elements of two deluges with the same key, like `join_by_key`,
+    /// but keeps the elements without a match on either side, pairing them with `None`.
     ///
     /// # Examples
+    ///
     /// ```
     /// use deluge::*;
     ///
     /// # futures::executor::block_on(async {
-    /// let result = (0..10).into_deluge()
-    ///     .last()
-    ///     .collect::<Vec<usize>>(None)
+    /// let mut result = [(1, "one"), (2, "two")]
+    ///     .into_deluge()
+    ///     .full_join_by_key([(2, 'b'), (3, 'c')].into_deluge(), |x| x.0, |y| y.0)
+    ///     .collect::<Vec<_>>(None)
     ///     .await;
-    ///
-    /// assert_eq!(vec![9], result);
+    /// result.sort();
+    ///
+    /// assert_eq!(
+    ///     vec![
+    ///         (None, Some((3, 'c'))),
+    ///         (Some((1, "one")), None),
+    ///         (Some((2, "two")), Some((2, 'b'))),
+    ///     ],
+    ///     result
+    /// );
     /// # });
-    ///
     /// ```
-    fn last(self) -> Last<Self>
+    fn full_join_by_key<'a, Del2, K, KF1, KF2>(
+        self,
+        other: Del2,
+        key_left: KF1,
+        key_right: KF2,
+    ) -> JoinByKey<'a, Self, Del2, K, KF1, KF2, FullJoin>
     where
-        Self: Sized,
+        Del2: Deluge + 'a,
+        K: Hash + Eq,
+        KF1: Fn(&Self::Item) -> K,
+        KF2: Fn(&Del2::Item) -> K,
+        Self: Sized + 'a,
     {
-        Last::new(self)
+        JoinByKey::new(self, other, key_left, key_right)
     }
 
-    /// Consumes at most `how_many` elements from the Deluge, ignoring the rest.
+    /// Alternates between the elements of two deluges,
+    /// continuing with the remaining one once the other runs out.
+    /// The elements of both are evaluated concurrently by the collector.
     ///
     /// # Examples
     ///
@@ -432,23 +799,35 @@ pub trait DelugeExt: Deluge {
     /// use deluge::*;
     ///
     /// # futures::executor::block_on(async {
-    /// let result = (0..100).into_deluge()
-    ///     .take(1)
-    ///     .fold(None, 0, |acc, x| async move { acc + x })
+    /// let result = [1, 3, 5, 7, 9]
+    ///     .into_deluge()
+    ///     .interleave([2, 4].into_deluge().map(|x| async move { x }))
+    ///     .collect::<Vec<usize>>(None)
     ///     .await;
     ///
-    /// assert_eq!(0, result);
+    /// assert_eq!(vec![1, 2, 3, 4, 5, 7, 9], result);
     /// # });
     /// ```
-    fn take(self, how_many: usize) -> Take<Self>
+    fn interleave<Del2>(self, other: Del2) -> Interleave<Self, Del2>
     where
+        Del2: Deluge<Item = Self::Item>,
         Self: Sized,
     {
-        Take::new(self, how_many)
+        Interleave::new(self, other)
     }
 
-    /// Combines two Deluges into one with elements being
-    /// tuples of subsequent elements from each
+    /// Pairs up the elements of two deluges with the same key,
+    /// leaving out the ones without a match on the other side.
+    ///
+    /// Both deluges are evaluated concurrently by the collector.
+    /// The one known to be smaller is loaded into a hash map,
+    /// while the pairs are produced as the elements of the other one resolve,
+    /// so their order is unspecified.
+    /// When a key repeats on both sides, every pair of its elements is produced.
+    /// The join can't tell how many pairs there are until the smaller side is evaluated though.
+    /// The concurrent collector gets a future for each element of the larger side and for each
+    /// pair known on top of that, while the parallel one, which takes all the futures up front,
+    /// gets one for each pair there might be. The extra futures resolve to `None`.
     ///
     /// # Examples
     ///
@@ -456,59 +835,60 @@ pub trait DelugeExt: Deluge {
     /// use deluge::*;
     ///
     /// # futures::executor::block_on(async {
-    /// let result = (0..100).rev()
-    ///     .into_deluge()
-    ///     .zip((0..90).into_deluge(), None)
-    ///     .collect::<Vec<(u64, u64)>>(None)
+    /// let users = [(1, "alice"), (2, "bob"), (3, "carol")].into_deluge();
+    /// let orders = [(10, 1), (11, 3), (12, 1), (13, 4)].into_deluge();
+    ///
+    /// let mut result = users
+    ///     .join_by_key(orders, |user| user.0, |order| order.1)
+    ///     .map(|(user, order)| async move { (user.1, order.0) })
+    ///     .collect::<Vec<_>>(None)
     ///     .await;
+    /// result.sort();
     ///
-    /// assert_eq!(result.len(), 90);
-    /// assert_eq!(result[0], (99, 0));
-    /// assert_eq!(result[1], (98, 1));
+    /// assert_eq!(vec![("alice", 10), ("alice", 12), ("carol", 11)], result);
     /// # });
     /// ```
-    fn zip<'a, Del2>(
+    fn join_by_key<'a, Del2, K, KF1, KF2>(
         self,
         other: Del2,
-        concurrency: impl Into<Option<usize>>,
-    ) -> Zip<'a, Self, Del2>
+        key_left: KF1,
+        key_right: KF2,
+    ) -> JoinByKey<'a, Self, Del2, K, KF1, KF2, InnerJoin>
     where
         Del2: Deluge + 'a,
-        Self: Sized,
+        K: Hash + Eq,
+        KF1: Fn(&Self::Item) -> K,
+        KF2: Fn(&Del2::Item) -> K,
+        Self: Sized + 'a,
     {
-        Zip::new(self, other, concurrency)
+        JoinByKey::new(self, other, key_left, key_right)
     }
 
-    /// Collects elements in the current `Deluge` into a collection with a desired concurrency
+    /// Returns the last element of the input deluge and then finishes
     ///
     /// # Examples
-    ///
     /// ```
     /// use deluge::*;
     ///
     /// # futures::executor::block_on(async {
-    /// let result = (0..100).into_deluge()
+    /// let result = (0..10).into_deluge()
+    ///     .last()
     ///     .collect::<Vec<usize>>(None)
     ///     .await;
     ///
-    /// assert_eq!(result.len(), 100);
+    /// assert_eq!(vec![9], result);
     /// # });
+    ///
     /// ```
-    fn collect<'a, C>(self, concurrency: impl Into<Option<usize>>) -> Collect<'a, Self, C>
+    fn last(self) -> Last<Self>
     where
-        C: Default + Extend<Self::Item>,
         Self: Sized,
     {
-        Collect::new(self, concurrency)
+        Last::new(self)
     }
 
-    /// Collects elements in the current `Deluge` into a collection
-    /// in parallel. Optionally accepts a degree of parallelism
-    /// and concurrency for each worker.
-    ///
-    /// If the number of workers is not specified, we will default to the number of logical cpus.
-    /// If concurrency per worker is not specified, we will default to the total number of
-    /// items in a current deluge divided by the number of workers.
+    /// Pairs up the elements of two deluges with the same key, like `join_by_key`,
+    /// but keeps the elements of this deluge without a match, pairing them with `None`.
     ///
     /// # Examples
     ///
@@ -516,298 +896,2791 @@ pub trait DelugeExt: Deluge {
     /// use deluge::*;
     ///
     /// # futures::executor::block_on(async {
-    /// let result = (0..100).into_deluge()
-    ///     .collect_par::<Vec<usize>>(None, None)
+    /// let mut result = [(1, "one"), (2, "two")]
+    ///     .into_deluge()
+    ///     .left_join_by_key([(2, 'b'), (3, 'c')].into_deluge(), |x| x.0, |y| y.0)
+    ///     .collect::<Vec<_>>(None)
     ///     .await;
+    /// result.sort();
     ///
-    /// assert_eq!(result.len(), 100);
+    /// assert_eq!(vec![((1, "one"), None), ((2, "two"), Some((2, 'b')))], result);
     /// # });
     /// ```
-    #[cfg(feature = "async-runtime")]
-    fn collect_par<'a, C>(
+    fn left_join_by_key<'a, Del2, K, KF1, KF2>(
         self,
-        worker_count: impl Into<Option<usize>>,
-        worker_concurrency: impl Into<Option<usize>>,
-    ) -> CollectPar<'a, Self, C>
+        other: Del2,
+        key_left: KF1,
+        key_right: KF2,
+    ) -> JoinByKey<'a, Self, Del2, K, KF1, KF2, LeftJoin>
     where
-        C: Default + Extend<Self::Item>,
+        Del2: Deluge + 'a,
+        K: Hash + Eq,
+        KF1: Fn(&Self::Item) -> K,
+        KF2: Fn(&Del2::Item) -> K,
+        Self: Sized + 'a,
+    {
+        JoinByKey::new(self, other, key_left, key_right)
+    }
+
+    /// Wraps the deluge in the left variant of `Either`,
+    /// so it has the same type as a differently built one wrapped with `right_deluge`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    /// use std::collections::HashSet;
+    /// use std::sync::Mutex;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let dedupe = true;
+    /// let deluge = [1, 1, 2, 3, 3].into_deluge();
+    /// let deluge = if dedupe {
+    ///     let seen = Mutex::new(HashSet::new());
+    ///     deluge
+    ///         .filter_map(move |x| {
+    ///             let unseen = seen.lock().unwrap().insert(x);
+    ///             async move { unseen.then_some(x) }
+    ///         })
+    ///         .left_deluge()
+    /// } else {
+    ///     deluge.right_deluge()
+    /// };
+    ///
+    /// let result = deluge.collect::<Vec<usize>>(None).await;
+    /// assert_eq!(vec![1, 2, 3], result);
+    /// # });
+    /// ```
+    fn left_deluge<R>(self) -> Either<Self, R>
+    where
+        R: Deluge<Item = Self::Item>,
         Self: Sized,
     {
-        CollectPar::new(self, worker_count, worker_concurrency)
+        Either::Left(self)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::into_deluge::IntoDeluge;
-    use crate::iter::iter;
-    use more_asserts::{assert_gt, assert_lt};
-    use std::sync::Arc;
-    use std::time::{Duration, Instant};
+    /// Limits the rate at which the collector starts evaluating elements
+    /// to `permits` elements per `interval`.
+    /// Up to `burst` elements can be started at once if the limit was not used up recently,
+    /// by default it is the same as `permits`.
+    ///
+    /// Unlike the concurrency limit of a collector, the rate limit holds
+    /// no matter how quickly the elements evaluate.
+    /// All the workers of a parallel collector share the same limit.
+    /// An element dropped before it gets to start, like the ones left over
+    /// when a collection is cancelled, gives its token back for a later element to use.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    /// # use std::time::{Duration, Instant};
+    ///
+    /// # let rt = tokio::runtime::Runtime::new().unwrap();
+    /// # rt.handle().block_on(async {
+    /// let start = Instant::now();
+    /// let result = (0..10)
+    ///     .into_deluge()
+    ///     .rate_limit(100, Duration::from_secs(1), 5)
+    ///     .collect::<Vec<usize>>(None)
+    ///     .await;
+    ///
+    /// assert_eq!(result.len(), 10);
+    /// // The first five elements start right away, the remaining ones one every 10ms
+    /// assert!(Instant::now() - start >= Duration::from_millis(50));
+    /// # });
+    /// ```
+    #[cfg(feature = "async-runtime")]
+    fn rate_limit(
+        self,
+        permits: usize,
+        interval: Duration,
+        burst: impl Into<Option<usize>>,
+    ) -> RateLimit<Self>
+    where
+        Self: Sized,
+    {
+        RateLimit::new(self, permits, interval, burst)
+    }
+
+    /// Wraps the deluge in the right variant of `Either`,
+    /// so it has the same type as a differently built one wrapped with `left_deluge`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let double = false;
+    /// let deluge = (0..4).into_deluge();
+    /// let deluge = if double {
+    ///     deluge.map(|x| async move { x * 2 }).left_deluge()
+    /// } else {
+    ///     deluge.right_deluge()
+    /// };
+    ///
+    /// let result = deluge.collect::<Vec<usize>>(None).await;
+    /// assert_eq!(vec![0, 1, 2, 3], result);
+    /// # });
+    /// ```
+    fn right_deluge<L>(self) -> Either<L, Self>
+    where
+        L: Deluge<Item = Self::Item>,
+        Self: Sized,
+    {
+        Either::Right(self)
+    }
+
+    /// Splits the deluge into `shards` deluges which share its elements,
+    /// with every element going to exactly one of them.
+    ///
+    /// The shards can be collected separately, including on different local tasks,
+    /// and each of them evaluates the elements it got.
+    /// With `SplitMode::RoundRobin` the shards take turns, and the elements meant for
+    /// a shard that was dropped go to the remaining ones.
+    /// With `SplitMode::NextFree` each element goes to the first shard asking for one,
+    /// so a shard collected with a concurrency limit takes more work as soon as it has room.
+    /// A shard that returned `None` keeps doing so, even if another shard is dropped later,
+    /// in which case the elements left to the dropped one aren't evaluated at all.
+    ///
+    /// Elements pulled out for a shard that isn't asking for them meanwhile are kept
+    /// in its queue with no cap, so the shards are meant to be collected alongside each other.
+    ///
+    /// The shards share the deluge, so they're only `Send` if it is `Sync`,
+    /// which the ones created with `into_deluge` aren't. The futures of the collectors
+    /// aren't `Send` either, so a shard is collected on a task of a `LocalSet`
+    /// or joined with the others on a single task, but it can't be spawned
+    /// on a multi-threaded runtime:
+    ///
+    /// ```compile_fail
+    /// use deluge::*;
+    ///
+    /// # let rt = tokio::runtime::Runtime::new().unwrap();
+    /// # rt.block_on(async {
+    /// for shard in (0..10).into_deluge().split(2, SplitMode::NextFree) {
+    ///     tokio::spawn(shard.collect::<Vec<usize>>(None));
+    /// }
+    /// # });
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # let rt = tokio::runtime::Runtime::new().unwrap();
+    /// let local = tokio::task::LocalSet::new();
+    /// # local.block_on(&rt, async {
+    /// let tasks = (0..100)
+    ///     .into_deluge()
+    ///     .map(|x| async move { x * 2 })
+    ///     .split(4, SplitMode::NextFree)
+    ///     .into_iter()
+    ///     .map(|shard| tokio::task::spawn_local(shard.collect::<Vec<usize>>(5)))
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let mut result = Vec::new();
+    /// for task in tasks {
+    ///     result.extend(task.await.unwrap());
+    /// }
+    /// result.sort();
+    ///
+    /// assert_eq!((0..100).map(|x| x * 2).collect::<Vec<usize>>(), result);
+    /// # });
+    /// ```
+    fn split<'a>(self, shards: usize, mode: SplitMode) -> Vec<Split<'a, Self>>
+    where
+        Self: Sized + 'a,
+    {
+        Split::new(self, shards, mode)
+    }
+
+    /// Splits the deluge into `shards` deluges, sending all the elements with the same key
+    /// returned by `key_fn` to the same one.
+    ///
+    /// The key is only known once an element is evaluated, so unlike with `split`
+    /// the elements are evaluated by the shards together,
+    /// as many at once as the shards are waiting for.
+    /// The elements meant for a shard that was dropped are discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut shards = (0..20).into_deluge().split_by_key(2, |x| x % 4);
+    /// let second = shards.pop().unwrap();
+    /// let first = shards.pop().unwrap();
+    ///
+    /// let (first, second) = futures::join!(
+    ///     first.collect::<Vec<usize>>(None),
+    ///     second.collect::<Vec<usize>>(None),
+    /// );
+    ///
+    /// assert_eq!(20, first.len() + second.len());
+    /// for x in first {
+    ///     assert!(second.iter().all(|y| x % 4 != y % 4));
+    /// }
+    /// # });
+    /// ```
+    fn split_by_key<'a, K, KF>(self, shards: usize, key_fn: KF) -> Vec<SplitByKey<'a, Self, KF>>
+    where
+        K: Hash,
+        KF: Fn(&Self::Item) -> K,
+        Self: Sized + 'a,
+    {
+        SplitByKey::new(self, shards, key_fn)
+    }
+
+    /// Consumes at most `how_many` elements from the Deluge, ignoring the rest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = (0..100).into_deluge()
+    ///     .take(1)
+    ///     .fold(None, 0, |acc, x| async move { acc + x })
+    ///     .await;
+    ///
+    /// assert_eq!(0, result);
+    /// # });
+    /// ```
+    fn take(self, how_many: usize) -> Take<Self>
+    where
+        Self: Sized,
+    {
+        Take::new(self, how_many)
+    }
+
+    /// Splits the deluge into `branches` deluges which each return every element.
+    ///
+    /// Each element is evaluated only once, by whichever branch gets to it first,
+    /// and the others receive a clone of the result.
+    /// At most `buffer` elements are evaluated or kept around ahead of the slowest branch,
+    /// so the faster ones wait for it to catch up.
+    /// That means the branches have to be collected at the same time,
+    /// collecting one after the other only works if the whole deluge fits in the buffer.
+    /// A dropped branch no longer holds the others back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let mut branches = (0..10)
+    ///     .into_deluge()
+    ///     .map(|x| async move { x * 2 })
+    ///     .tee(2, 4)
+    ///     .into_iter();
+    /// let doubled = branches.next().unwrap();
+    /// let sum = branches
+    ///     .next()
+    ///     .unwrap()
+    ///     .fold(None, 0, |acc, x| async move { acc + x });
+    ///
+    /// let (doubled, sum) = futures::join!(doubled.collect::<Vec<usize>>(None), sum);
+    ///
+    /// assert_eq!(vec![0, 2, 4, 6, 8, 10, 12, 14, 16, 18], doubled);
+    /// assert_eq!(90, sum);
+    /// # });
+    /// ```
+    fn tee<'a>(self, branches: usize, buffer: usize) -> Vec<Tee<'a, Self>>
+    where
+        Self::Item: Clone,
+        Self: Sized + 'a,
+    {
+        Tee::new(self, branches, buffer)
+    }
+
+    /// Limits the time each element is allowed to take to evaluate.
+    /// The time is measured from the moment a collector starts evaluating an element.
+    ///
+    /// With `ErrorOnTimeout`, elements that evaluate in time become `Ok(element)`
+    /// and the ones that don't become `Err(Elapsed)`.
+    /// With `DropOnTimeout`, the elements that don't evaluate in time are dropped
+    /// and the rest is left as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    /// # use std::time::Duration;
+    ///
+    /// # let rt = tokio::runtime::Runtime::new().unwrap();
+    /// # rt.handle().block_on(async {
+    /// let result = (0..4)
+    ///     .into_deluge()
+    ///     .map(|x| async move {
+    ///         if x == 2 {
+    ///             futures::future::pending::<()>().await;
+    ///         }
+    ///         x
+    ///     })
+    ///     .timeout(Duration::from_millis(50), ErrorOnTimeout)
+    ///     .collect::<Vec<Result<usize, Elapsed>>>(None)
+    ///     .await;
+    ///
+    /// assert_eq!(result[1], Ok(1));
+    /// assert!(result[2].is_err());
+    /// # });
+    /// ```
+    #[cfg(feature = "async-runtime")]
+    fn timeout<K>(self, duration: Duration, _on_timeout: K) -> Timeout<Self, K>
+    where
+        K: TimeoutKind<Self::Item>,
+        Self: Sized,
+    {
+        Timeout::new(self, duration)
+    }
+
+    /// Combines two Deluges into one with elements being
+    /// tuples of subsequent elements from each
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = (0..100).rev()
+    ///     .into_deluge()
+    ///     .zip((0..90).into_deluge(), None)
+    ///     .collect::<Vec<(u64, u64)>>(None)
+    ///     .await;
+    ///
+    /// assert_eq!(result.len(), 90);
+    /// assert_eq!(result[0], (99, 0));
+    /// assert_eq!(result[1], (98, 1));
+    /// # });
+    /// ```
+    fn zip<'a, Del2>(self, other: Del2, concurrency: impl Into<Concurrency>) -> Zip<'a, Self, Del2>
+    where
+        Del2: Deluge + 'a,
+        Self: Sized,
+    {
+        Zip::new(self, other, concurrency)
+    }
+
+    /// Collects elements in the current `Deluge` into a collection with a desired concurrency
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = (0..100).into_deluge()
+    ///     .collect::<Vec<usize>>(None)
+    ///     .await;
+    ///
+    /// assert_eq!(result.len(), 100);
+    /// # });
+    /// ```
+    fn collect<'a, C>(self, concurrency: impl Into<Concurrency>) -> Collect<'a, Self, C>
+    where
+        C: Default + Extend<Self::Item>,
+        Self: Sized,
+    {
+        Collect::new(self, concurrency)
+    }
+
+    /// Collects elements in the current `Deluge` into a collection with a desired concurrency,
+    /// giving up on the elements that did not evaluate before the `deadline`.
+    ///
+    /// Resolves to the collection along with a report of the elements that are missing from it.
+    /// Elements that evaluated before the deadline are returned in order,
+    /// even if some of the elements preceding them did not make it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// # let rt = tokio::runtime::Runtime::new().unwrap();
+    /// # rt.handle().block_on(async {
+    /// let (result, report) = (0..10)
+    ///     .into_deluge()
+    ///     .map(|x| async move {
+    ///         if x % 2 == 1 {
+    ///             tokio::time::sleep(Duration::from_secs(10)).await;
+    ///         }
+    ///         x
+    ///     })
+    ///     .collect_until::<Vec<usize>>(None, Instant::now() + Duration::from_millis(50))
+    ///     .await;
+    ///
+    /// assert_eq!(result, vec![0, 2, 4, 6, 8]);
+    /// assert_eq!(report.in_flight(), &[1, 3, 5, 7, 9]);
+    /// assert!(report.not_started_from().is_none());
+    /// # });
+    /// ```
+    #[cfg(feature = "async-runtime")]
+    fn collect_until<'a, C>(
+        self,
+        concurrency: impl Into<Concurrency>,
+        deadline: Instant,
+    ) -> CollectUntil<'a, Self, C>
+    where
+        C: Default + Extend<Self::Item>,
+        Self: Sized,
+    {
+        CollectUntil::new(self, concurrency, deadline)
+    }
+
+    /// Collects elements in the current `Deluge` into a collection
+    /// in parallel. Optionally accepts a degree of parallelism
+    /// and concurrency for each worker.
+    ///
+    /// If the number of workers is not specified, we will default to the number of logical cpus.
+    /// If concurrency per worker is not specified, we will default to the total number of
+    /// items in a current deluge divided by the number of workers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result = (0..100).into_deluge()
+    ///     .collect_par::<Vec<usize>>(None, None)
+    ///     .await;
+    ///
+    /// assert_eq!(result.len(), 100);
+    /// # });
+    /// ```
+    fn collect_par<'a, C>(
+        self,
+        worker_count: impl Into<Option<usize>>,
+        worker_concurrency: impl Into<Concurrency>,
+    ) -> CollectPar<'a, Self, C>
+    where
+        C: Default + Extend<Self::Item>,
+        Self: Sized,
+    {
+        CollectPar::new(self, worker_count, worker_concurrency)
+    }
+
+    /// Evaluates the elements of the deluge, expanding each one with `f`
+    /// into an output and the child elements to expand next, until no new elements show up.
+    /// The outputs are collected into a collection with a desired concurrency.
+    ///
+    /// The children are expanded by the same collector,
+    /// so the concurrency limit applies to the whole crawl.
+    /// They are expanded breadth first unless a different `CrawlOrder` is set with `order`,
+    /// which is also the order of the outputs.
+    /// Use `max_depth` to stop after a number of levels and `dedup` or `dedup_by_key`
+    /// to expand each element only once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use deluge::*;
+    ///
+    /// # futures::executor::block_on(async {
+    /// let result: Vec<usize> = [1]
+    ///     .into_deluge()
+    ///     .crawl(4, |x| async move { (x, [x * 2, x * 3]) })
+    ///     .max_depth(2)
+    ///     .dedup()
+    ///     .await;
+    ///
+    /// assert_eq!(vec![1, 2, 3, 4, 6, 9], result);
+    /// # });
+    /// ```
+    fn crawl<'a, C, Out, I, Fut, F>(
+        self,
+        concurrency: impl Into<Concurrency>,
+        f: F,
+    ) -> Crawl<'a, Self, F, Fut, Out, C>
+    where
+        F: Fn(Self::Item) -> Fut,
+        Fut: Future<Output = (Out, I)>,
+        I: IntoIterator<Item = Self::Item>,
+        C: Default + Extend<Out>,
+        Self: Sized + 'a,
+    {
+        Crawl::new(self, concurrency, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cancellation::CancellationToken;
+    use crate::concurrency::{AdaptiveConcurrency, ConcurrencyLimiter};
+    use crate::into_deluge::IntoDeluge;
+    use crate::ops::catch_unwind::{PanicInfo, PanicPolicy};
+    #[cfg(feature = "tokio")]
+    use crate::runtime::{Runtime, TokioRuntime};
+    use futures::FutureExt;
+    use more_asserts::{assert_gt, assert_le, assert_lt};
+    use std::collections::HashMap;
+    use std::panic::AssertUnwindSafe;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::{Duration, Instant};
     use tokio::sync::Mutex;
 
-    #[tokio::test]
-    async fn map_can_be_created() {
-        [1, 2, 3, 4].into_deluge().map(|x| async move { x * 2 });
-        assert_eq!(2, 2);
+    #[tokio::test]
+    async fn map_can_be_created() {
+        [1, 2, 3, 4].into_deluge().map(|x| async move { x * 2 });
+        assert_eq!(2, 2);
+    }
+
+    #[tokio::test]
+    async fn we_can_collect() {
+        let result = [1, 2, 3, 4].into_deluge().collect::<Vec<usize>>(None).await;
+
+        assert_eq!(vec![1, 2, 3, 4], result);
+    }
+
+    #[tokio::test]
+    async fn any_works() {
+        let result = [1, 2, 3, 4]
+            .into_deluge()
+            .any(None, |x| async move { x == 4 })
+            .await;
+
+        assert!(result);
+    }
+
+    #[tokio::test]
+    async fn any_short_circuits() {
+        let evaluated = Arc::new(Mutex::new(Vec::new()));
+
+        let result = [1, 2, 3, 4, 5, 6, 7]
+            .into_deluge()
+            .any(1, |x| {
+                let evaluated = evaluated.clone();
+                async move {
+                    {
+                        let mut evaluated = evaluated.lock().await;
+                        evaluated.push(x);
+                    }
+                    tokio::time::sleep(Duration::from_millis(100 - 10 * x)).await;
+                    x == 2
+                }
+            })
+            .await;
+
+        assert!(result);
+        // We might evaluate a little bit more than we should have, but not much more
+        assert_lt!(Arc::try_unwrap(evaluated).unwrap().into_inner().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn any_par_works() {
+        let result = [1, 2, 3, 4]
+            .into_deluge()
+            .any_par(None, None, |x| async move { x == 4 })
+            .await;
+
+        assert!(result);
+    }
+
+    #[tokio::test]
+    async fn any_par_short_circuits() {
+        let evaluated = Arc::new(Mutex::new(Vec::new()));
+
+        let result = [1, 2, 3, 4, 5, 6, 7]
+            .into_deluge()
+            .any_par(2, 1, |x| {
+                let evaluated = evaluated.clone();
+                async move {
+                    {
+                        let mut evaluated = evaluated.lock().await;
+                        evaluated.push(x);
+                    }
+                    tokio::time::sleep(Duration::from_millis(100 - 10 * x)).await;
+                    x == 2
+                }
+            })
+            .await;
+
+        assert!(result);
+        // We might evaluate a little bit more than we should have, but not much more
+        assert_lt!(Arc::try_unwrap(evaluated).unwrap().into_inner().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn all_works() {
+        let result = [1, 2, 3, 4]
+            .into_deluge()
+            .all(None, |x| async move { x < 5 })
+            .await;
+
+        assert!(result);
+    }
+
+    #[tokio::test]
+    async fn all_short_circuits() {
+        let evaluated = Arc::new(Mutex::new(Vec::new()));
+
+        let result = [1, 2, 3, 4, 5, 6, 7]
+            .into_deluge()
+            .all(1, |x| {
+                let evaluated = evaluated.clone();
+                async move {
+                    {
+                        let mut evaluated = evaluated.lock().await;
+                        evaluated.push(x);
+                    }
+                    tokio::time::sleep(Duration::from_millis(100 - 10 * x)).await;
+                    x < 3
+                }
+            })
+            .await;
+
+        assert!(!result);
+        // We might evaluate a little bit more than we should have, but not much more
+        assert_lt!(Arc::try_unwrap(evaluated).unwrap().into_inner().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn all_par_works() {
+        let result = [1, 2, 3, 4]
+            .into_deluge()
+            .all_par(None, None, |x| async move { x < 5 })
+            .await;
+
+        assert!(result);
+    }
+
+    #[tokio::test]
+    async fn all_par_short_circuits() {
+        let evaluated = Arc::new(Mutex::new(Vec::new()));
+
+        let result = [1, 2, 3, 4, 5, 6, 7]
+            .into_deluge()
+            .all_par(2, 1, |x| {
+                let evaluated = evaluated.clone();
+                async move {
+                    {
+                        let mut evaluated = evaluated.lock().await;
+                        evaluated.push(x);
+                    }
+                    tokio::time::sleep(Duration::from_millis(100 - 10 * x)).await;
+                    x < 3
+                }
+            })
+            .await;
+
+        assert!(!result);
+        // We might evaluate a little bit more than we should have, but not much more
+        assert_lt!(Arc::try_unwrap(evaluated).unwrap().into_inner().len(), 7);
+    }
+
+    #[tokio::test]
+    async fn chain_works() {
+        let result = [1, 2, 3, 4]
+            .into_deluge()
+            .chain([5, 6, 7, 8].into_deluge())
+            .collect::<Vec<usize>>(None)
+            .await;
+
+        assert_eq!(result.len(), 8);
+        assert_eq!(result, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[tokio::test]
+    async fn count_works() {
+        let result = [1, 2, 3, 4].into_deluge().count();
+
+        assert_eq!(result, 4);
+    }
+
+    #[tokio::test]
+    async fn we_can_mult() {
+        let result = [1, 2, 3, 4]
+            .into_deluge()
+            .map(|x| async move { x * 2 })
+            .collect::<Vec<usize>>(None)
+            .await;
+
+        assert_eq!(vec![2, 4, 6, 8], result);
+    }
+
+    #[tokio::test]
+    async fn map_keyed_runs_same_keys_sequentially() {
+        let running = Arc::new(Mutex::new(HashMap::new()));
+        let start = Instant::now();
+
+        let result = (0..20)
+            .into_deluge()
+            .map_keyed(
+                |idx| idx % 4,
+                |idx| {
+                    let running = running.clone();
+                    async move {
+                        {
+                            let mut running = running.lock().await;
+                            let running_for_key = running.entry(idx % 4).or_insert(0);
+                            assert_eq!(*running_for_key, 0);
+                            *running_for_key += 1;
+                        }
+                        // Later elements finish faster, so only the key queue keeps them in order
+                        tokio::time::sleep(Duration::from_millis(50 - 2 * idx as u64)).await;
+                        {
+                            let mut running = running.lock().await;
+                            *running.get_mut(&(idx % 4)).unwrap() -= 1;
+                        }
+                        (idx, Instant::now())
+                    }
+                },
+            )
+            .collect::<Vec<(usize, Instant)>>(None)
+            .await;
+
+        let iteration_took = Instant::now() - start;
+        // Five elements per key evaluated sequentially, four keys evaluated concurrently
+        assert_gt!(iteration_took.as_millis(), 150);
+        assert_lt!(iteration_took.as_millis(), 300);
+
+        assert_eq!(result.len(), 20);
+        for (idx, (elem, finished_at)) in result.iter().enumerate() {
+            assert_eq!(idx, *elem);
+            if idx >= 4 {
+                assert_lt!(result[idx - 4].1, *finished_at);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn map_keyed_waiting_elements_leave_room_for_other_keys() {
+        let start = Instant::now();
+        let running = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let most_running = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let result = [("a", 100), ("a", 10), ("b", 10), ("b", 10), ("c", 10)]
+            .into_deluge()
+            .map_keyed(
+                |(key, _)| *key,
+                |(key, millis)| {
+                    let (running, most_running) = (running.clone(), most_running.clone());
+                    async move {
+                        let now_running =
+                            running.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                        most_running.fetch_max(now_running, std::sync::atomic::Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(millis)).await;
+                        running.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                        (key, start.elapsed())
+                    }
+                },
+            )
+            .collect::<Vec<(&str, Duration)>>(2)
+            .await;
+
+        // The second "a" waits for the first one without taking up the other slot
+        assert_eq!(
+            result.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            vec!["a", "a", "b", "b", "c"]
+        );
+        assert_gt!(result[1].1, result[0].1);
+        for (_, finished_at) in &result[2..] {
+            assert_lt!(finished_at.as_millis(), 80);
+        }
+        assert_eq!(most_running.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn map_keyed_releases_filtered_elements() {
+        let result = (0..10)
+            .into_deluge()
+            .filter_map(|idx| async move {
+                if idx % 3 == 0 {
+                    None
+                } else {
+                    Some(idx)
+                }
+            })
+            .map_keyed(|idx| idx % 2, |idx| async move { idx * 2 })
+            .collect::<Vec<usize>>(2)
+            .await;
+
+        assert_eq!(result, vec![2, 4, 8, 10, 14, 16]);
+    }
+
+    #[tokio::test]
+    async fn map_keyed_works_with_last() {
+        let result = (0..10)
+            .into_deluge()
+            .map_keyed(|_| 0, |idx| async move { idx })
+            .last()
+            .collect::<Vec<usize>>(None)
+            .await;
+
+        assert_eq!(result, vec![9]);
+    }
+
+    #[tokio::test]
+    async fn collect_can_be_dropped_with_elements_in_flight() {
+        // The queued elements hold on to the key queue inside the deluge until they are dropped
+        let mut collect = (0..10)
+            .into_deluge()
+            .map_keyed(
+                |idx| idx % 2,
+                |idx| async move {
+                    futures::future::pending::<()>().await;
+                    idx
+                },
+            )
+            .collect::<Vec<usize>>(None);
+
+        assert!(futures::poll!(&mut collect).is_pending());
+        drop(collect);
+    }
+
+    #[tokio::test]
+    async fn collect_can_be_moved_after_polling() {
+        let mut collect = (0..10)
+            .into_deluge()
+            .map(|idx| async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                idx
+            })
+            .collect::<Vec<usize>>(None);
+        assert!(futures::poll!(&mut collect).is_pending());
+
+        let moved = vec![collect];
+        let result = moved.into_iter().next().unwrap().await;
+        assert_eq!(result, (0..10).collect::<Vec<usize>>());
+    }
+
+    // Alternates between providing an element and running out of them
+    struct Blinking(std::cell::Cell<bool>);
+
+    impl Blinking {
+        fn new() -> Self {
+            Self(std::cell::Cell::new(false))
+        }
+    }
+
+    impl Deluge for Blinking {
+        type Item = usize;
+        type Output<'x> = std::future::Ready<Option<usize>>;
+
+        fn next(&self) -> Option<Self::Output<'_>> {
+            self.0.set(!self.0.get());
+            self.0.get().then(|| std::future::ready(Some(1)))
+        }
+    }
+
+    fn assert_fused<Del: Deluge>(deluge: Del) {
+        while deluge.next().is_some() {}
+        for _ in 0..3 {
+            assert!(deluge.next().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn deluges_are_fused() {
+        assert_fused((0..4).into_deluge());
+        assert_fused((0..4).into_deluge().map(|x| async move { x }));
+        assert_fused((0..4).into_deluge().filter_map(|x| async move { Some(x) }));
+        assert_fused((0..4).into_deluge().chain((4..8).into_deluge()));
+        assert_fused((0..4).into_deluge().take(2));
+        assert_fused((0..4).into_deluge().take(10));
+        assert_fused((0..4).into_deluge().first());
+        assert_fused((0..4).into_deluge().last());
+        assert_fused((0..4).into_deluge().catch_unwind());
+        assert_fused(
+            (0..4)
+                .into_deluge()
+                .map_keyed(|x| x % 2, |x| async move { x }),
+        );
+        assert_fused(
+            (0..4)
+                .into_deluge()
+                .map_weighted(2, |_| 1, |x| async move { x }),
+        );
+        assert_fused((0..4).into_deluge().fuse());
+        assert_fused((0..4).into_deluge().zip((0..2).into_deluge(), None));
+    }
+
+    #[cfg(feature = "async-runtime")]
+    #[tokio::test]
+    async fn runtime_deluges_are_fused() {
+        assert_fused((0..4).into_deluge().map_blocking(|x| x));
+        assert_fused(
+            (0..4)
+                .into_deluge()
+                .timeout(Duration::from_secs(1), DropOnTimeout),
+        );
+        assert_fused(
+            (0..4)
+                .into_deluge()
+                .rate_limit(10, Duration::from_secs(1), None),
+        );
+        assert_fused((0..4).into_deluge().map_retry(
+            RetryPolicy::fixed(Duration::from_millis(1)),
+            |x| async move { Ok::<usize, ()>(x) },
+        ));
+    }
+
+    #[tokio::test]
+    async fn deluges_stay_fused_over_unfused_inputs() {
+        assert_fused(Blinking::new().fuse());
+        assert_fused(Blinking::new().chain(Blinking::new()));
+        assert_fused(Blinking::new().take(5));
+        assert_fused(Blinking::new().first());
+        assert_fused(Blinking::new().last());
+        assert_fused(Blinking::new().fuse().map(|x| async move { x }));
+
+        let mut calls = 0;
+        let unfused_iter = std::iter::from_fn(move || {
+            calls += 1;
+            (calls % 2 == 1).then_some(calls)
+        });
+        assert_fused(unfused_iter.into_deluge());
+    }
+
+    #[tokio::test]
+    async fn size_hints_propagate() {
+        let deluge = (0..10).into_deluge();
+        assert_eq!(deluge.size_hint(), (10, Some(10)));
+        deluge.next();
+        assert_eq!(deluge.size_hint(), (9, Some(9)));
+
+        let mapped = (0..10).into_deluge().map(|x| async move { x });
+        assert_eq!(mapped.size_hint(), (10, Some(10)));
+        let filtered = (0..10).into_deluge().filter_map(|x| async move { Some(x) });
+        assert_eq!(filtered.size_hint(), (0, Some(10)));
+
+        let chained = (0..10).into_deluge().chain((0..5).into_deluge());
+        assert_eq!(chained.size_hint(), (15, Some(15)));
+        for _ in 0..12 {
+            chained.next();
+        }
+        assert_eq!(chained.size_hint(), (3, Some(3)));
+
+        assert_eq!((0..10).into_deluge().take(4).size_hint(), (4, Some(4)));
+        assert_eq!((0..10).into_deluge().take(20).size_hint(), (10, Some(10)));
+        let filtered = (0..10).into_deluge().filter_map(|x| async move { Some(x) });
+        assert_eq!(filtered.take(4).size_hint(), (0, Some(4)));
+
+        let first = (0..10).into_deluge().first();
+        assert_eq!(first.size_hint(), (1, Some(1)));
+        first.next();
+        assert_eq!(first.size_hint(), (0, Some(0)));
+        assert_eq!((0..10).into_deluge().last().size_hint(), (1, Some(1)));
+
+        assert_eq!(Blinking::new().size_hint(), (0, None));
+        assert_eq!(Blinking::new().take(3).size_hint(), (0, Some(3)));
+    }
+
+    #[tokio::test]
+    async fn zip_size_hint_is_the_shorter_side() {
+        let zipped = (0..10).into_deluge().zip((0..4).into_deluge(), None);
+        assert_eq!(zipped.size_hint(), (4, Some(4)));
+        zipped.next();
+        assert_eq!(zipped.size_hint(), (3, Some(3)));
+
+        let filtered = (0..4).into_deluge().filter_map(|x| async move { Some(x) });
+        let zipped = (0..10).into_deluge().zip(filtered, None);
+        assert_eq!(zipped.size_hint(), (0, Some(4)));
+    }
+
+    // Claims to have more elements than it actually has
+    struct Overpromising(std::cell::Cell<usize>);
+
+    impl Deluge for Overpromising {
+        type Item = usize;
+        type Output<'x> = std::future::Ready<Option<usize>>;
+
+        fn next(&self) -> Option<Self::Output<'_>> {
+            let left = self.0.get().checked_sub(1)?;
+            self.0.set(left);
+            Some(std::future::ready(Some(left)))
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (10, None)
+        }
+    }
+
+    #[tokio::test]
+    async fn zip_size_hint_stays_within_the_preloaded_elements() {
+        let zipped = (0..10)
+            .into_deluge()
+            .zip(Overpromising(std::cell::Cell::new(3)), None);
+        assert_eq!(zipped.size_hint(), (3, Some(3)));
+        zipped.next();
+        assert_eq!(zipped.size_hint(), (2, Some(2)));
+    }
+
+    struct Pipeline {
+        deluge: BoxDeluge<'static, usize>,
+    }
+
+    #[tokio::test]
+    async fn boxed_deluges_can_be_built_at_runtime() {
+        let steps = ["double", "skip_odd", "increment"];
+
+        let mut deluge = (0..10).into_deluge().boxed();
+        for step in steps {
+            deluge = match step {
+                "double" => deluge.map(|x| async move { x * 2 }).boxed(),
+                "skip_odd" => deluge
+                    .filter_map(|x| async move { (x % 4 == 0).then_some(x) })
+                    .boxed(),
+                _ => deluge.map(|x| async move { x + 1 }).boxed(),
+            };
+        }
+        let pipeline = Pipeline { deluge };
+
+        assert_eq!(pipeline.deluge.size_hint(), (0, Some(10)));
+        let result = pipeline.deluge.collect::<Vec<usize>>(None).await;
+        assert_eq!(result, vec![1, 5, 9, 13, 17]);
+    }
+
+    #[tokio::test]
+    async fn local_boxed_deluges_can_borrow() {
+        let offset = 10;
+        let deluge = (0..4)
+            .into_deluge()
+            .map(|x| {
+                let offset = &offset;
+                async move { x + offset }
+            })
+            .boxed_local();
+
+        let result = deluge.collect::<Vec<usize>>(None).await;
+        assert_eq!(result, vec![10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn boxed_deluges_can_be_sent_to_another_thread() {
+        let deluge = (0..10).into_deluge().map(|x| async move { x * 2 }).boxed();
+
+        let result = std::thread::spawn(move || {
+            futures::executor::block_on(deluge.collect::<Vec<usize>>(3))
+        })
+        .join()
+        .unwrap();
+        assert_eq!(result, (0..10).map(|x| x * 2).collect::<Vec<usize>>());
+    }
+
+    #[tokio::test]
+    async fn differently_built_boxed_deluges_chain() {
+        let result = (0..4)
+            .into_deluge()
+            .map(|x| async move { x * 2 })
+            .boxed()
+            .chain((0..2).into_deluge().boxed())
+            .collect::<Vec<usize>>(None)
+            .await;
+        assert_eq!(result, vec![0, 2, 4, 6, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn either_picks_a_branch() {
+        let build = |double: bool| {
+            let deluge = (0..4).into_deluge();
+            if double {
+                deluge.map(|x| async move { x * 2 }).left_deluge()
+            } else {
+                deluge
+                    .filter_map(|x| async move { (x % 2 == 0).then_some(x) })
+                    .right_deluge()
+            }
+        };
+
+        assert_eq!(build(true).size_hint(), (4, Some(4)));
+        assert_eq!(build(false).size_hint(), (0, Some(4)));
+        assert_eq!(
+            build(true).collect::<Vec<usize>>(None).await,
+            vec![0, 2, 4, 6]
+        );
+        assert_eq!(build(false).collect::<Vec<usize>>(None).await, vec![0, 2]);
+    }
+
+    #[tokio::test]
+    async fn differently_built_deluges_chain() {
+        let offset = 10;
+        let result = (0..4)
+            .into_deluge()
+            .map(|x| {
+                let offset = &offset;
+                async move { x + offset }
+            })
+            .chain(
+                (0..4)
+                    .into_deluge()
+                    .filter_map(|x| async move { (x % 2 == 0).then_some(x) }),
+            )
+            .collect::<Vec<usize>>(None)
+            .await;
+
+        assert_eq!(result, vec![10, 11, 12, 13, 0, 2]);
+    }
+
+    #[tokio::test]
+    async fn interleave_alternates_between_concurrent_deluges() {
+        let delayed = |values: Vec<u64>| {
+            values.into_deluge().map(|x| async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                x
+            })
+        };
+
+        let start = Instant::now();
+        let result = delayed(vec![1, 3, 5, 7, 9])
+            .interleave(delayed(vec![2, 4]))
+            .collect::<Vec<u64>>(None)
+            .await;
+
+        assert_eq!(result, vec![1, 2, 3, 4, 5, 7, 9]);
+        assert_lt!((Instant::now() - start).as_millis(), 150);
+    }
+
+    // Each element takes 50ms, while counting how many of the elements
+    // of the same source are being evaluated at once
+    fn delayed_source(
+        values: Vec<(u64, usize)>,
+        in_flight: Arc<std::sync::Mutex<(usize, usize)>>,
+    ) -> impl Deluge<Item = (u64, usize)> {
+        values.into_deluge().map(move |x| {
+            let in_flight = in_flight.clone();
+            async move {
+                {
+                    let mut in_flight = in_flight.lock().unwrap();
+                    in_flight.0 += 1;
+                    in_flight.1 = std::cmp::max(in_flight.0, in_flight.1);
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                in_flight.lock().unwrap().0 -= 1;
+                x
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn merge_sorted_by_evaluates_heads_concurrently() {
+        let in_flight = (0..3)
+            .map(|_| Arc::new(std::sync::Mutex::new((0, 0))))
+            .collect::<Vec<_>>();
+        let sources = [
+            vec![(1, 0), (4, 0), (4, 0), (9, 0)],
+            vec![(2, 1), (4, 1), (8, 1)],
+            vec![(3, 2), (5, 2), (6, 2), (7, 2)],
+        ]
+        .into_iter()
+        .zip(in_flight.iter())
+        .map(|(values, in_flight)| delayed_source(values, in_flight.clone()))
+        .collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let mut sources = sources.into_iter();
+        let result = sources
+            .next()
+            .unwrap()
+            .merge_sorted_by(sources, |a, b| a.0.cmp(&b.0))
+            .with_lookahead(2)
+            .collect::<Vec<(u64, usize)>>(None)
+            .await;
+
+        // Equal elements keep the order of their sources
+        assert_eq!(
+            result,
+            vec![
+                (1, 0),
+                (2, 1),
+                (3, 2),
+                (4, 0),
+                (4, 0),
+                (4, 1),
+                (5, 2),
+                (6, 2),
+                (7, 2),
+                (8, 1),
+                (9, 0)
+            ]
+        );
+        for in_flight in in_flight {
+            assert_eq!(in_flight.lock().unwrap().1, 2);
+        }
+        // Two elements of each source at a time
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 100);
+        assert_lt!(iteration_took.as_millis(), 300);
+    }
+
+    #[tokio::test]
+    async fn merge_sorted_by_skips_filtered_elements() {
+        let result = (0..10)
+            .into_deluge()
+            .filter_map(|x| async move { (x % 3 == 0).then_some(x) })
+            .boxed()
+            .merge_sorted_by([(0..10).into_deluge().boxed()], |a, b| a.cmp(b))
+            .collect::<Vec<usize>>(2)
+            .await;
+
+        assert_eq!(result, vec![0, 0, 1, 2, 3, 3, 4, 5, 6, 6, 7, 8, 9, 9]);
+    }
+
+    // Counts the futures created out of it
+    struct Counted {
+        values: std::sync::Mutex<std::vec::IntoIter<usize>>,
+        created: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl Deluge for Counted {
+        type Item = usize;
+        type Output<'x> = std::future::Ready<Option<usize>>;
+
+        fn next(&self) -> Option<Self::Output<'_>> {
+            let value = self.values.lock().unwrap().next()?;
+            self.created
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Some(std::future::ready(Some(value)))
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.values.lock().unwrap().size_hint()
+        }
+    }
+
+    #[tokio::test]
+    async fn merge_sorted_by_only_pulls_within_the_lookahead() {
+        let created = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let source = |values: Vec<usize>| Counted {
+            values: std::sync::Mutex::new(values.into_iter()),
+            created: created.clone(),
+        };
+        let merge = source(vec![0, 2, 4, 6, 8])
+            .merge_sorted_by([source(vec![1, 3, 5, 7, 9])], |a, b| a.cmp(b))
+            .with_lookahead(2);
+
+        // The sources are known to have more elements, so none are pulled to hand out futures
+        let futures = std::iter::from_fn(|| merge.next()).collect::<Vec<_>>();
+        assert_eq!(futures.len(), 10);
+        assert_eq!(created.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        let mut futures = futures.into_iter();
+        assert_eq!(futures.next().unwrap().await, Some(0));
+        assert_eq!(created.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        let rest = futures::future::join_all(futures).await;
+        assert_eq!(rest, (1..10).map(Some).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn merge_sorted_by_only_pulls_filtered_sources_within_the_lookahead() {
+        let created = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let source = |values: Vec<usize>| {
+            Counted {
+                values: std::sync::Mutex::new(values.into_iter()),
+                created: created.clone(),
+            }
+            .filter_map(|x| async move { (x % 3 != 0).then_some(x) })
+        };
+        let merge = source(vec![0, 2, 4, 6, 8])
+            .merge_sorted_by([source(vec![1, 3, 5, 7, 9])], |a, b| a.cmp(b))
+            .with_lookahead(2);
+
+        // Any of the elements might be filtered out, so only the lookahead is pulled
+        // and the rest of the futures resolve to `None` if there are fewer elements
+        let futures = std::iter::from_fn(|| merge.next()).collect::<Vec<_>>();
+        assert_eq!(futures.len(), 10);
+        assert_eq!(created.load(std::sync::atomic::Ordering::SeqCst), 4);
+
+        let merged = futures::future::join_all(futures).await;
+        assert_eq!(
+            merged.into_iter().flatten().collect::<Vec<_>>(),
+            vec![1, 2, 4, 5, 7, 8]
+        );
+
+        // A collector without a limit waits for the merge instead of draining the sources
+        let created = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let source = |values: Vec<usize>| {
+            Counted {
+                values: std::sync::Mutex::new(values.into_iter()),
+                created: created.clone(),
+            }
+            .filter_map(|x| async move {
+                if x == 0 {
+                    futures::future::pending::<()>().await;
+                }
+                Some(x)
+            })
+        };
+        let merge = source(vec![0, 2, 4, 6, 8])
+            .merge_sorted_by([source(vec![1, 3, 5, 7, 9])], |a, b| a.cmp(b))
+            .with_lookahead(2);
+        let mut collect = std::pin::pin!(merge.collect::<Vec<usize>>(None));
+        assert!(futures::poll!(collect.as_mut()).is_pending());
+        assert_eq!(created.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn merge_sorted_by_survives_a_panicked_element() {
+        let result = (0..10)
+            .into_deluge()
+            .map(panic_on_odd)
+            .merge_sorted_by([(0..5).into_deluge().map(panic_on_odd)], |a, b| a.cmp(b))
+            .collect::<Vec<usize>>(None)
+            .on_panic(PanicPolicy::Drop)
+            .await;
+
+        assert_eq!(result, vec![0, 0, 2, 2, 4, 4, 6, 8]);
+    }
+
+    #[tokio::test]
+    async fn merge_sorted_by_works_in_parallel() {
+        let in_flight = Arc::new(std::sync::Mutex::new((0, 0)));
+        let even = (0..20).step_by(2).map(|x| (x, 0)).collect();
+        let odd = (1..20).step_by(2).map(|x| (x, 1)).collect();
+        let even = delayed_source(even, in_flight.clone());
+        let odd = delayed_source(odd, in_flight);
+
+        let result = even
+            .merge_sorted_by([odd], |a, b| a.0.cmp(&b.0))
+            .collect_par::<Vec<(u64, usize)>>(4, None)
+            .await;
+
+        assert_eq!(
+            result.into_iter().map(|(x, _)| x).collect::<Vec<_>>(),
+            (0..20).collect::<Vec<u64>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn join_by_key_evaluates_both_sides_concurrently() {
+        let in_flight = Arc::new(std::sync::Mutex::new((0, 0)));
+        let users = delayed_source(vec![(1, 0), (2, 0), (3, 0)], in_flight.clone());
+        let orders = delayed_source(
+            vec![(10, 1), (11, 3), (12, 1), (13, 4), (14, 2)],
+            in_flight.clone(),
+        );
+
+        let start = Instant::now();
+        let mut result = users
+            .join_by_key(orders, |user| user.0, |order| order.1 as u64)
+            .map(|(user, order)| async move { (user.0, order.0) })
+            .collect::<Vec<(u64, u64)>>(None)
+            .await;
+        result.sort();
+
+        assert_eq!(result, vec![(1, 10), (1, 12), (2, 14), (3, 11)]);
+        assert_eq!(in_flight.lock().unwrap().1, 8);
+        let iteration_took = Instant::now() - start;
+        assert_lt!(iteration_took.as_millis(), 100);
+    }
+
+    #[tokio::test]
+    async fn joins_keep_the_unmatched_elements() {
+        let left = || {
+            (0..6)
+                .into_deluge()
+                .filter_map(|x| async move { (x % 2 == 0).then_some(x) })
+        };
+        let right = || (1..6).into_deluge().map(|x| async move { x * 2 });
+        let key = |x: &usize| *x;
+
+        // The smaller side gets loaded into the table, so both orientations are covered
+        let mut inner = left()
+            .join_by_key(right(), key, key)
+            .collect::<Vec<_>>(None)
+            .await;
+        inner.sort();
+        assert_eq!(inner, vec![(2, 2), (4, 4)]);
+
+        let mut inner = right()
+            .join_by_key((0..16).into_deluge(), key, key)
+            .collect::<Vec<_>>(2)
+            .await;
+        inner.sort();
+        assert_eq!(inner, vec![(2, 2), (4, 4), (6, 6), (8, 8), (10, 10)]);
+
+        let mut left_joined = left()
+            .left_join_by_key((3..6).into_deluge(), key, key)
+            .collect::<Vec<_>>(None)
+            .await;
+        left_joined.sort();
+        assert_eq!(left_joined, vec![(0, None), (2, None), (4, Some(4))]);
+
+        let mut full = (3..6)
+            .into_deluge()
+            .full_join_by_key(left(), key, key)
+            .collect::<Vec<_>>(3)
+            .await;
+        full.sort();
+        assert_eq!(
+            full,
+            vec![
+                (None, Some(0)),
+                (None, Some(2)),
+                (Some(3), None),
+                (Some(4), Some(4)),
+                (Some(5), None)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn join_by_key_pairs_up_keys_repeating_on_both_sides() {
+        let left = || [(1, 'a'), (1, 'b'), (1, 'c'), (2, 'd')].into_deluge();
+        let right = || {
+            [(1, 'x'), (2, 'y'), (1, 'z'), (1, 'w'), (3, 'v')]
+                .into_deluge()
+                .map(|x| async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    x
+                })
+        };
+        let key = |x: &(usize, char)| x.0;
+        let mut expected = vec![];
+        for l in ['a', 'b', 'c'] {
+            for r in ['w', 'x', 'z'] {
+                expected.push((l, r));
+            }
+        }
+        expected.push(('d', 'y'));
+
+        // The futures are handed out as fast as the join lets them
+        let mut all_at_once = left()
+            .join_by_key(right(), key, key)
+            .map(|(l, r)| async move { (l.1, r.1) })
+            .collect::<Vec<(char, char)>>(None)
+            .await;
+        all_at_once.sort();
+        assert_eq!(all_at_once, expected);
+
+        // The futures are handed out as the earlier ones resolve
+        let mut one_by_one = right()
+            .join_by_key(left(), key, key)
+            .map(|(r, l)| async move { (l.1, r.1) })
+            .collect::<Vec<(char, char)>>(2)
+            .await;
+        one_by_one.sort();
+        assert_eq!(one_by_one, expected);
+    }
+
+    // Counts the futures handed out by the wrapped deluge
+    struct Issued<Del> {
+        deluge: Del,
+        issued: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl<Del: Deluge> Deluge for Issued<Del> {
+        type Item = Del::Item;
+        type Output<'x>
+            = Del::Output<'x>
+        where
+            Self: 'x;
+
+        fn next(&self) -> Option<Self::Output<'_>> {
+            let next = self.deluge.next();
+            if next.is_some() {
+                self.issued
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+            next
+        }
+
+        fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+            self.deluge.poll_ready(cx)
+        }
+    }
+
+    #[tokio::test]
+    async fn join_by_key_hands_out_a_future_per_pair_when_keys_are_unique() {
+        let issued = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let joined = Issued {
+            deluge: (0..2000)
+                .into_deluge()
+                .join_by_key((0..2000).into_deluge(), |x| *x, |x| *x),
+            issued: issued.clone(),
+        };
+
+        let result = joined.collect::<Vec<(usize, usize)>>(None).await;
+
+        assert_eq!(result.len(), 2000);
+        assert_eq!(issued.load(std::sync::atomic::Ordering::SeqCst), 2000);
+    }
+
+    #[tokio::test]
+    async fn joins_survive_a_panicked_element() {
+        let mut result = (0..10)
+            .into_deluge()
+            .map(panic_on_odd)
+            .full_join_by_key((0..3).into_deluge(), |x| *x, |x| *x)
+            .collect::<Vec<_>>(None)
+            .on_panic(PanicPolicy::Drop)
+            .await;
+        result.sort();
+
+        assert_eq!(
+            result,
+            vec![
+                (None, Some(1)),
+                (Some(0), Some(0)),
+                (Some(2), Some(2)),
+                (Some(4), None),
+                (Some(6), None),
+                (Some(8), None)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_skips_the_exhausted_first_deluge() {
+        let result = Blinking::new()
+            .chain(Blinking::new())
+            .collect::<Vec<usize>>(None)
+            .await;
+
+        assert_eq!(result, vec![1, 1]);
+    }
+
+    #[tokio::test]
+    async fn map_weighted_limits_the_total_cost() {
+        let in_flight = Arc::new(std::sync::Mutex::new((0, 0)));
+        let costs = [5, 5, 20, 5, 5];
+
+        let start = Instant::now();
+        let result = costs
+            .into_deluge()
+            .map_weighted(
+                10,
+                |cost| *cost,
+                |cost| {
+                    let in_flight = in_flight.clone();
+                    async move {
+                        let cost_before = {
+                            let mut in_flight = in_flight.lock().unwrap();
+                            let cost_before = in_flight.0;
+                            in_flight.0 += cost;
+                            in_flight.1 = std::cmp::max(in_flight.0, in_flight.1);
+                            cost_before
+                        };
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        in_flight.lock().unwrap().0 -= cost;
+                        (cost, cost_before)
+                    }
+                },
+            )
+            .collect::<Vec<(usize, usize)>>(None)
+            .await;
+
+        // Depending on when the oversized element gets in line
+        // it either runs between two rounds of smaller elements or splits one of them
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 150);
+        assert_lt!(iteration_took.as_millis(), 250);
+
+        // The oversized element runs alone instead of stalling the pipeline
+        assert_eq!(result[2], (20, 0));
+        assert_eq!(
+            result.iter().map(|(cost, _)| *cost).collect::<Vec<_>>(),
+            costs.to_vec()
+        );
+        assert_eq!(in_flight.lock().unwrap().1, 20);
+    }
+
+    #[tokio::test]
+    async fn map_weighted_budget_spans_the_parallel_workers() {
+        let in_flight = Arc::new(std::sync::Mutex::new((0, 0)));
+        let costs = [1, 6, 2, 6, 1, 6, 2, 6];
+
+        let result = costs
+            .into_deluge()
+            .map_weighted(
+                8,
+                |cost| *cost,
+                |cost| {
+                    let in_flight = in_flight.clone();
+                    async move {
+                        {
+                            let mut in_flight = in_flight.lock().unwrap();
+                            in_flight.0 += cost;
+                            in_flight.1 = std::cmp::max(in_flight.0, in_flight.1);
+                        }
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        in_flight.lock().unwrap().0 -= cost;
+                        cost
+                    }
+                },
+            )
+            .collect_par::<Vec<usize>>(4, None)
+            .await;
+
+        assert_eq!(result, costs.to_vec());
+        assert_le!(in_flight.lock().unwrap().1, 8);
+    }
+
+    #[cfg(feature = "async-runtime")]
+    #[tokio::test]
+    async fn map_blocking_keeps_the_executor_free() {
+        let start = Instant::now();
+        let ticker = async {
+            let mut ticks = 0;
+            while Instant::now() - start < Duration::from_millis(90) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                ticks += 1;
+            }
+            ticks
+        };
+
+        let collect = (0..8)
+            .into_deluge()
+            .map_blocking(|idx| {
+                std::thread::sleep(Duration::from_millis(50 - idx * 5));
+                idx
+            })
+            .collect::<Vec<u64>>(4);
+
+        let (result, ticks) = futures::join!(collect, ticker);
+
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 85);
+        assert_lt!(iteration_took.as_millis(), 140);
+
+        assert_eq!(result, (0..8).collect::<Vec<_>>());
+        // The single threaded executor kept ticking while the closures were sleeping
+        assert_gt!(ticks, 5);
+    }
+
+    #[cfg(feature = "async-runtime")]
+    #[tokio::test]
+    async fn map_blocking_panics_reach_the_collector() {
+        let result = (0..6)
+            .into_deluge()
+            .map_blocking(|idx| {
+                if idx % 2 == 1 {
+                    panic!("odd element {idx}");
+                }
+                idx
+            })
+            .collect::<Vec<usize>>(None)
+            .on_panic(PanicPolicy::Drop)
+            .await;
+
+        assert_eq!(result, vec![0, 2, 4]);
+    }
+
+    #[tokio::test]
+    async fn concurrent_pipelines_accept_non_send_values() {
+        let local = tokio::task::LocalSet::new();
+        let seen = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+
+        let task_seen = seen.clone();
+        let task = local.spawn_local(async move {
+            let result = (0..10)
+                .map(std::rc::Rc::new)
+                .into_deluge()
+                .map(|idx| {
+                    let seen = task_seen.clone();
+                    async move {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                        seen.borrow_mut().push(*idx);
+                        idx
+                    }
+                })
+                .filter_map(|idx| async move { (*idx % 2 == 0).then_some(idx) })
+                .collect::<Vec<std::rc::Rc<usize>>>(3)
+                .await;
+
+            let sum = result
+                .clone()
+                .into_deluge()
+                .fold(None, 0, |acc, idx| async move { acc + *idx })
+                .await;
+
+            (result, sum)
+        });
+        let (result, sum) = local.run_until(task).await.unwrap();
+
+        assert_eq!(
+            result.iter().map(|idx| **idx).collect::<Vec<_>>(),
+            vec![0, 2, 4, 6, 8]
+        );
+        assert_eq!(sum, 20);
+        assert_eq!(seen.borrow().len(), 10);
+    }
+
+    #[cfg(all(feature = "rayon", feature = "async-runtime"))]
+    #[tokio::test]
+    async fn map_blocking_runs_on_rayon() {
+        let result = (0..20)
+            .into_deluge()
+            .map_blocking(|idx| (idx, rayon::current_thread_index().is_some()))
+            .with_runtime(crate::runtime::RayonRuntime::default())
+            .collect::<Vec<(usize, bool)>>(None)
+            .await;
+
+        assert_eq!(result, (0..20).map(|idx| (idx, true)).collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "async-runtime")]
+    #[tokio::test]
+    async fn map_retry_retries_failed_elements() {
+        let attempts = Arc::new(Mutex::new(HashMap::new()));
+
+        let result = (0..10)
+            .into_deluge()
+            .map_retry(
+                RetryPolicy::exponential(Duration::from_millis(5), 2).max_attempts(4),
+                |idx| {
+                    let attempts = attempts.clone();
+                    async move {
+                        let mut attempts = attempts.lock().await;
+                        let attempt = attempts.entry(idx).or_insert(0);
+                        *attempt += 1;
+
+                        // Every element fails as many times as its remainder
+                        if *attempt > idx % 5 {
+                            Ok(idx)
+                        } else {
+                            Err(*attempt)
+                        }
+                    }
+                },
+            )
+            .collect::<Vec<Result<usize, usize>>>(3)
+            .await;
+
+        let expected = (0..10)
+            .map(|idx| if idx % 5 < 4 { Ok(idx) } else { Err(4) })
+            .collect::<Vec<_>>();
+        assert_eq!(result, expected);
+
+        let attempts = attempts.lock().await;
+        for idx in 0..10 {
+            assert_eq!(attempts[&idx], std::cmp::min(idx % 5 + 1, 4));
+        }
+    }
+
+    #[tokio::test]
+    async fn filter_map_works() {
+        let result = [1, 2, 3, 4]
+            .into_deluge()
+            .filter_map(|x| async move {
+                if x % 2 == 0 {
+                    Some("yes")
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<&str>>(None)
+            .await;
+
+        assert_eq!(vec!["yes", "yes"], result);
+    }
+
+    #[tokio::test]
+    async fn we_can_go_between_values_and_deluges() {
+        let result = [1, 2, 3, 4]
+            .into_deluge()
+            .map(|x| async move { x * 2 })
+            .collect::<Vec<usize>>(None)
+            .await
+            .into_deluge()
+            .map(|x| async move { x * 2 })
+            .fold(None, 0, |acc, x| async move { acc + x })
+            .await;
+
+        assert_eq!(result, 40);
+    }
+
+    #[tokio::test]
+    async fn we_wait_cuncurrently() {
+        let start = Instant::now();
+        let result = (0..100)
+            .into_deluge()
+            .map(|idx| async move {
+                tokio::time::sleep(Duration::from_millis(100 - (idx as u64))).await;
+                idx
+            })
+            .collect::<Vec<usize>>(None)
+            .await;
+
+        let iteration_took = Instant::now() - start;
+        assert_lt!(iteration_took.as_millis(), 200);
+
+        assert_eq!(result.len(), 100);
+
+        result
+            .into_iter()
+            .enumerate()
+            .for_each(|(idx, elem)| assert_eq!(idx, elem));
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit() {
+        let start = Instant::now();
+        let result = (0..15)
+            .into_deluge()
+            .map(|idx| async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                idx
+            })
+            .collect::<Vec<usize>>(5)
+            .await;
+
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 150);
+        assert_lt!(iteration_took.as_millis(), 200);
+
+        assert_eq!(result.len(), 15);
+    }
+
+    #[tokio::test]
+    async fn shared_concurrency_limit() {
+        let limiter = ConcurrencyLimiter::new(5);
+        let running = Arc::new(std::sync::Mutex::new((0, 0)));
+
+        let pipeline = |offset: usize| {
+            let running = running.clone();
+            (0..10).into_deluge().map(move |idx| {
+                let running = running.clone();
+                async move {
+                    {
+                        let mut running = running.lock().unwrap();
+                        running.0 += 1;
+                        running.1 = std::cmp::max(running.0, running.1);
+                    }
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    running.lock().unwrap().0 -= 1;
+                    idx + offset
+                }
+            })
+        };
+
+        let start = Instant::now();
+        let (first, sum, any) = futures::join!(
+            pipeline(0).collect::<Vec<usize>>(&limiter),
+            pipeline(10).fold(&limiter, 0, |acc, x| async move { acc + x }),
+            pipeline(20).any(&limiter, |x| async move { x == 29 }),
+        );
+
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 300);
+        assert_lt!(iteration_took.as_millis(), 400);
+
+        assert_eq!(first, (0..10).collect::<Vec<usize>>());
+        assert_eq!(sum, 145);
+        assert!(any);
+        assert_eq!(running.lock().unwrap().1, 5);
+        assert_eq!(limiter.available(), 5);
+    }
+
+    #[tokio::test]
+    async fn shared_concurrency_limit_in_parallel() {
+        let limiter = ConcurrencyLimiter::new(5);
+
+        let start = Instant::now();
+        let (first, second) = futures::join!(
+            (0..10)
+                .into_deluge()
+                .map(|idx| async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    idx
+                })
+                .collect_par::<Vec<usize>>(4, &limiter),
+            (0..10)
+                .into_deluge()
+                .map(|idx| async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    idx
+                })
+                .collect::<Vec<usize>>(&limiter),
+        );
+
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 200);
+        assert_lt!(iteration_took.as_millis(), 300);
+
+        assert_eq!(first, (0..10).collect::<Vec<usize>>());
+        assert_eq!(second, (0..10).collect::<Vec<usize>>());
+        assert_eq!(limiter.available(), 5);
+    }
+
+    #[tokio::test]
+    async fn adaptive_concurrency_grows_while_fast() {
+        let adaptive = AdaptiveConcurrency::new(Duration::from_millis(100)).max_limit(16);
+
+        let result = (0..100)
+            .into_deluge()
+            .map(|idx| async move {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                idx
+            })
+            .collect::<Vec<usize>>(&adaptive)
+            .await;
+
+        assert_eq!(result, (0..100).collect::<Vec<usize>>());
+        assert_gt!(adaptive.current_limit(), 5);
+        assert_lt!(adaptive.current_limit(), 17);
+    }
+
+    #[tokio::test]
+    async fn adaptive_concurrency_backs_off_on_failures() {
+        let adaptive = AdaptiveConcurrency::new(Duration::from_secs(1)).initial_limit(16);
+
+        let result = (0..50)
+            .into_deluge()
+            .map(|idx| async move {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                if idx < 40 {
+                    Err(idx)
+                } else {
+                    Ok(idx)
+                }
+            })
+            .collect::<Vec<Result<usize, usize>>>(&adaptive)
+            .backoff_when(Result::is_err)
+            .await;
+
+        assert_eq!(result.len(), 50);
+        // Ten successes after hitting the floor grow the limit only slightly
+        assert_lt!(adaptive.current_limit(), 5);
+    }
+
+    #[tokio::test]
+    async fn adaptive_concurrency_backs_off_on_latency() {
+        let adaptive = AdaptiveConcurrency::new(Duration::from_millis(20)).initial_limit(16);
+
+        (0..30)
+            .into_deluge()
+            .map(|idx| async move {
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                idx
+            })
+            .collect::<Vec<usize>>(&adaptive)
+            .await;
+
+        assert_eq!(adaptive.current_limit(), 1);
+    }
+
+    #[tokio::test]
+    async fn adaptive_concurrency_in_parallel() {
+        let adaptive = AdaptiveConcurrency::new(Duration::from_secs(1)).initial_limit(16);
+
+        let result = (0..50)
+            .into_deluge()
+            .map(|idx| async move {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                Err::<usize, usize>(idx)
+            })
+            .collect_par::<Vec<Result<usize, usize>>>(4, &adaptive)
+            .backoff_when(Result::is_err)
+            .await;
+
+        assert_eq!(result, (0..50).map(Err).collect::<Vec<_>>());
+        assert_eq!(adaptive.current_limit(), 1);
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_elements_in_flight() {
+        let token = CancellationToken::new();
+
+        let start = Instant::now();
+        let (result, _) = futures::join!(
+            (0..10)
+                .into_deluge()
+                .map(|idx| async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    idx
+                })
+                .collect::<Vec<usize>>(3)
+                .with_cancellation(&token),
+            async {
+                tokio::time::sleep(Duration::from_millis(75)).await;
+                token.shutdown();
+            }
+        );
+
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 100);
+        assert_lt!(iteration_took.as_millis(), 150);
+        assert_eq!(result, (0..6).collect::<Vec<usize>>());
+    }
+
+    #[tokio::test]
+    async fn cancel_drops_elements_in_flight() {
+        let token = CancellationToken::new();
+
+        let collector = (0..10)
+            .into_deluge()
+            .map(|idx| async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                idx
+            })
+            .collect::<Vec<usize>>(3)
+            .with_cancellation(&token);
+        futures::pin_mut!(collector);
+
+        let mut delivered = Vec::new();
+        let start = Instant::now();
+        while let Some(idx) = futures::StreamExt::next(&mut collector).await {
+            delivered.push(idx);
+            if idx == 1 {
+                token.cancel();
+            }
+        }
+
+        assert_lt!((Instant::now() - start).as_millis(), 100);
+        assert_eq!(delivered, vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn shutdown_drains_parallel_elements_in_flight() {
+        let token = CancellationToken::new();
+
+        let (result, _) = futures::join!(
+            (0..20)
+                .into_deluge()
+                .map(|idx| async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    idx
+                })
+                .collect_par::<Vec<usize>>(2, 2)
+                .with_cancellation(&token),
+            async {
+                tokio::time::sleep(Duration::from_millis(75)).await;
+                token.shutdown();
+            }
+        );
+
+        assert_eq!(result, (0..8).collect::<Vec<usize>>());
+    }
+
+    #[tokio::test]
+    async fn collect_does_not_allocate_for_the_whole_size_hint() {
+        let token = CancellationToken::new();
+        token.cancel();
+
+        // Room for every element of the hint wouldn't even fit in the address space
+        let result = (0..usize::MAX)
+            .into_deluge()
+            .collect::<Vec<usize>>(None)
+            .with_cancellation(&token)
+            .await;
+
+        assert!(result.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_parallel_collection() {
+        let token = CancellationToken::new();
+
+        let start = Instant::now();
+        let (result, _) = futures::join!(
+            (0..20)
+                .into_deluge()
+                .map(|idx| async move {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    idx
+                })
+                .collect_par::<Vec<usize>>(2, 2)
+                .with_cancellation(&token),
+            async {
+                tokio::time::sleep(Duration::from_millis(75)).await;
+                token.cancel();
+            }
+        );
+
+        assert_lt!((Instant::now() - start).as_millis(), 100);
+        assert_eq!(result, (0..4).collect::<Vec<usize>>());
+    }
+
+    #[cfg(feature = "async-runtime")]
+    #[tokio::test]
+    async fn collect_until_returns_partial_results() {
+        let start = Instant::now();
+        let (result, report) = (0..20)
+            .into_deluge()
+            .map(|idx| async move {
+                tokio::time::sleep(Duration::from_millis(if idx == 2 { 500 } else { 30 })).await;
+                idx
+            })
+            .collect_until::<Vec<usize>>(5, start + Duration::from_millis(105))
+            .await;
+
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 100);
+        assert_lt!(iteration_took.as_millis(), 150);
+
+        // One slot is taken by the slow element, the other four run three rounds
+        assert_eq!(result, vec![0, 1, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        assert_eq!(report.in_flight(), &[2, 13, 14, 15, 16]);
+        assert_eq!(report.not_started_from(), Some(17));
+        assert!(!report.is_complete());
+    }
+
+    #[cfg(feature = "async-runtime")]
+    #[tokio::test]
+    async fn collect_until_completes_before_the_deadline() {
+        let (result, report) = (0..20)
+            .into_deluge()
+            .collect_until::<Vec<usize>>(None, Instant::now() + Duration::from_millis(100))
+            .await;
+
+        assert_eq!(result, (0..20).collect::<Vec<usize>>());
+        assert!(report.is_complete());
+    }
+
+    async fn panic_on_odd(idx: usize) -> usize {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        if idx % 2 == 1 {
+            panic!("odd element {idx}");
+        }
+        idx
+    }
+
+    #[tokio::test]
+    async fn panicked_elements_are_dropped() {
+        let result = (0..10)
+            .into_deluge()
+            .map(panic_on_odd)
+            .collect::<Vec<usize>>(3)
+            .on_panic(PanicPolicy::Drop)
+            .await;
+
+        assert_eq!(result, vec![0, 2, 4, 6, 8]);
+    }
+
+    #[tokio::test]
+    async fn panic_is_resumed_after_draining() {
+        let delivered = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let collector = (0..10)
+            .into_deluge()
+            .map(panic_on_odd)
+            .collect::<Vec<usize>>(3)
+            .on_panic(PanicPolicy::Resume);
+        let panic = AssertUnwindSafe(async {
+            futures::pin_mut!(collector);
+            while let Some(idx) = futures::StreamExt::next(&mut collector).await {
+                delivered.lock().unwrap().push(idx);
+            }
+        })
+        .catch_unwind()
+        .await
+        .unwrap_err();
+
+        assert_eq!(*delivered.lock().unwrap(), vec![0, 2, 4, 6, 8]);
+        assert_eq!(panic.downcast_ref::<String>().unwrap(), "odd element 1");
+    }
+
+    #[tokio::test]
+    async fn catch_unwind_reports_panics() {
+        let result = (0..4)
+            .into_deluge()
+            .map(panic_on_odd)
+            .catch_unwind()
+            .collect::<Vec<Result<usize, PanicInfo>>>(None)
+            .await;
+
+        let messages = result
+            .iter()
+            .map(|result| match result {
+                Ok(idx) => idx.to_string(),
+                Err(panic) => panic.message().unwrap().to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["0", "odd element 1", "2", "odd element 3"]);
+    }
+
+    #[tokio::test]
+    async fn collect_par_keeps_the_order_of_a_few_elements() {
+        let result = (0..10)
+            .into_deluge()
+            .map(|idx| async move { idx * 2 })
+            .collect_par::<Vec<usize>>(3, 2)
+            .await;
+
+        assert_eq!(result, (0..10).map(|idx| idx * 2).collect::<Vec<usize>>());
+    }
+
+    #[tokio::test]
+    async fn collect_par_takes_elements_that_are_not_send() {
+        let result = (0..10)
+            .into_deluge()
+            .map(|idx| async move { std::rc::Rc::new(idx) })
+            .collect_par::<Vec<std::rc::Rc<usize>>>(3, 2)
+            .await;
+
+        assert_eq!(
+            result.into_iter().map(|idx| *idx).collect::<Vec<usize>>(),
+            (0..10).collect::<Vec<usize>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn panicked_elements_are_dropped_in_parallel() {
+        let result = (0..10)
+            .into_deluge()
+            .map(panic_on_odd)
+            .collect_par::<Vec<usize>>(2, 2)
+            .on_panic(PanicPolicy::Drop)
+            .await;
+
+        assert_eq!(result, vec![0, 2, 4, 6, 8]);
     }
 
     #[tokio::test]
-    async fn we_can_collect() {
-        let result = [1, 2, 3, 4].into_deluge().collect::<Vec<usize>>(None).await;
+    async fn panic_is_resumed_after_draining_in_parallel() {
+        let panic = AssertUnwindSafe(
+            (0..10)
+                .into_deluge()
+                .map(panic_on_odd)
+                .collect_par::<Vec<usize>>(2, 2)
+                .on_panic(PanicPolicy::Resume),
+        )
+        .catch_unwind()
+        .await
+        .unwrap_err();
+
+        assert!(panic
+            .downcast_ref::<String>()
+            .unwrap()
+            .starts_with("odd element"));
+    }
 
-        assert_eq!(vec![1, 2, 3, 4], result);
+    #[cfg(feature = "tokio")]
+    #[derive(Clone, Default)]
+    struct CountingRuntime {
+        sleeps: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[cfg(feature = "tokio")]
+    impl Runtime for CountingRuntime {
+        type Sleep = tokio::time::Sleep;
+
+        fn sleep(&self, duration: Duration) -> Self::Sleep {
+            self.sleeps
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            TokioRuntime.sleep(duration)
+        }
     }
 
+    #[cfg(feature = "tokio")]
     #[tokio::test]
-    async fn any_works() {
-        let result = [1, 2, 3, 4]
+    async fn operations_use_the_provided_runtime() {
+        let runtime = CountingRuntime::default();
+
+        let result = (0..10)
             .into_deluge()
-            .any(None, |x| async move { x == 4 })
-            .await;
+            .timeout(Duration::from_millis(100), ErrorOnTimeout)
+            .with_runtime(runtime.clone())
+            .collect_until::<Vec<Result<usize, Elapsed>>>(
+                None,
+                Instant::now() + Duration::from_secs(1),
+            )
+            .with_runtime(runtime.clone())
+            .await
+            .0;
 
-        assert!(result);
+        assert_eq!(result, (0..10).map(Ok).collect::<Vec<_>>());
+        assert_eq!(runtime.sleeps.load(std::sync::atomic::Ordering::SeqCst), 11);
     }
 
     #[tokio::test]
-    async fn any_short_circuits() {
-        let evaluated = Arc::new(Mutex::new(Vec::new()));
-
-        let result = [1, 2, 3, 4, 5, 6, 7]
+    async fn tee_evaluates_each_element_once() {
+        let evaluated = Arc::new(std::sync::Mutex::new(0));
+        let counter = evaluated.clone();
+        let mut branches = (0..20)
             .into_deluge()
-            .any(1, |x| {
-                let evaluated = evaluated.clone();
+            .map(move |x| {
+                let evaluated = counter.clone();
                 async move {
-                    {
-                        let mut evaluated = evaluated.lock().await;
-                        evaluated.push(x);
-                    }
-                    tokio::time::sleep(Duration::from_millis(100 - 10 * x)).await;
-                    x == 2
+                    *evaluated.lock().unwrap() += 1;
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    x
                 }
             })
-            .await;
+            .filter_map(|x| async move { (x % 5 != 0).then_some(x) })
+            .tee(3, 20);
 
-        assert!(result);
-        // We might evaluate a little bit more than we should have, but not much more
-        assert_lt!(Arc::try_unwrap(evaluated).unwrap().into_inner().len(), 4);
+        let third = branches.pop().unwrap();
+        let second = branches.pop().unwrap();
+        let first = branches.pop().unwrap();
+
+        let start = Instant::now();
+        let (first, second, third) = futures::join!(
+            first.collect::<Vec<usize>>(None),
+            second.collect::<Vec<usize>>(4),
+            third.fold(None, 0, |acc, x| async move { acc + x }),
+        );
+
+        let expected = (0..20).filter(|x| x % 5 != 0).collect::<Vec<usize>>();
+        assert_eq!(first, expected);
+        assert_eq!(second, expected);
+        assert_eq!(third, expected.iter().sum::<usize>());
+        assert_eq!(*evaluated.lock().unwrap(), 20);
+
+        let iteration_took = Instant::now() - start;
+        assert_lt!(iteration_took.as_millis(), 100);
     }
 
     #[tokio::test]
-    async fn any_par_works() {
-        let result = [1, 2, 3, 4]
-            .into_deluge()
-            .any_par(None, None, |x| async move { x == 4 })
-            .await;
+    async fn tee_is_held_back_by_the_slowest_branch() {
+        let in_flight = Arc::new(std::sync::Mutex::new((0, 0)));
+        let values = (0..6).map(|x| (x, 0)).collect();
+        let mut branches = delayed_source(values, in_flight.clone()).tee(2, 2);
+
+        let slow = branches.pop().unwrap().map(|x| async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            x
+        });
+        let fast = branches.pop().unwrap();
 
-        assert!(result);
+        let start = Instant::now();
+        let fast = async {
+            let result = fast.collect::<Vec<(u64, usize)>>(None).await;
+            (result, Instant::now() - start)
+        };
+        let ((fast, fast_took), slow) = futures::join!(fast, slow.collect::<Vec<_>>(1));
+
+        assert_eq!(fast, slow);
+        assert_eq!(in_flight.lock().unwrap().1, 2);
+        // The fast branch can only get two elements ahead of the slow one
+        assert_gt!(fast_took.as_millis(), 400);
     }
 
     #[tokio::test]
-    async fn any_par_short_circuits() {
-        let evaluated = Arc::new(Mutex::new(Vec::new()));
+    async fn dropped_tee_branches_stop_holding_back_the_rest() {
+        let mut branches = (0..10).into_deluge().tee(3, 1);
+        branches.truncate(1);
 
-        let result = [1, 2, 3, 4, 5, 6, 7]
-            .into_deluge()
-            .any_par(2, 1, |x| {
-                let evaluated = evaluated.clone();
-                async move {
-                    {
-                        let mut evaluated = evaluated.lock().await;
-                        evaluated.push(x);
-                    }
-                    tokio::time::sleep(Duration::from_millis(100 - 10 * x)).await;
-                    x == 2
-                }
-            })
-            .await;
+        let result = branches.pop().unwrap().collect::<Vec<usize>>(None).await;
+        assert_eq!(result, (0..10).collect::<Vec<usize>>());
+    }
 
-        assert!(result);
-        // We might evaluate a little bit more than we should have, but not much more
-        assert_lt!(Arc::try_unwrap(evaluated).unwrap().into_inner().len(), 5);
+    #[tokio::test]
+    async fn tee_branches_survive_a_panicked_element() {
+        let mut branches = (0..10).into_deluge().map(panic_on_odd).tee(2, 8);
+        let second = branches.pop().unwrap();
+        let first = branches.pop().unwrap();
+
+        let (first, second) = futures::join!(
+            first
+                .collect::<Vec<usize>>(None)
+                .on_panic(PanicPolicy::Drop),
+            second
+                .collect::<Vec<usize>>(None)
+                .on_panic(PanicPolicy::Drop),
+        );
+
+        assert_eq!(first, vec![0, 2, 4, 6, 8]);
+        assert_eq!(second, vec![0, 2, 4, 6, 8]);
     }
 
     #[tokio::test]
-    async fn all_works() {
-        let result = [1, 2, 3, 4]
-            .into_deluge()
-            .all(None, |x| async move { x < 5 }).await;
+    async fn tee_branches_can_be_collected_in_parallel() {
+        let mut branches = (0..100).into_deluge().tee(2, 8);
+        let second = branches.pop().unwrap();
+        let first = branches.pop().unwrap();
+
+        let (mut first, second) = futures::join!(
+            first.collect_par::<Vec<usize>>(4, None),
+            second.collect::<Vec<usize>>(None),
+        );
+        first.sort();
+
+        assert_eq!(first, second);
+        assert_eq!(second, (0..100).collect::<Vec<usize>>());
+    }
 
-        assert!(result);
+    #[tokio::test]
+    async fn split_takes_turns_between_shards() {
+        let mut shards = (0..10).into_deluge().split(3, SplitMode::RoundRobin);
+        let third = shards.pop().unwrap();
+        let second = shards.pop().unwrap();
+        let first = shards.pop().unwrap();
+
+        // Collecting one shard at a time works, the others' elements wait unevaluated
+        let third = third.collect::<Vec<usize>>(None).await;
+        let first = first.collect::<Vec<usize>>(None).await;
+        let second = second.collect::<Vec<usize>>(None).await;
+
+        assert_eq!(first, vec![0, 3, 6, 9]);
+        assert_eq!(second, vec![1, 4, 7]);
+        assert_eq!(third, vec![2, 5, 8]);
     }
 
     #[tokio::test]
-    async fn all_short_circuits() {
-        let evaluated = Arc::new(Mutex::new(Vec::new()));
+    async fn split_hands_the_elements_of_dropped_shards_over() {
+        let mut shards = (0..10).into_deluge().split(3, SplitMode::RoundRobin);
+        let third = shards.pop().unwrap();
+        let second = shards.pop().unwrap();
+        let first = shards.pop().unwrap();
+
+        let first = first.take(2).collect::<Vec<usize>>(None).await;
+        drop(second);
+        let third = third.collect::<Vec<usize>>(None).await;
+
+        assert_eq!(first, vec![0, 3]);
+        // The third shard takes the dropped shard's element and its turns
+        assert_eq!(third, vec![2, 1, 4, 5, 6, 7, 8, 9]);
+    }
 
-        let result = [1, 2, 3, 4, 5, 6, 7]
-            .into_deluge()
-            .all(1, |x| {
-                let evaluated = evaluated.clone();
-                async move {
-                    {
-                        let mut evaluated = evaluated.lock().await;
-                        evaluated.push(x);
-                    }
-                    tokio::time::sleep(Duration::from_millis(100 - 10 * x)).await;
-                    x < 3
-                }
-            })
-            .await;
+    #[tokio::test]
+    async fn split_shards_stay_finished_when_another_is_dropped() {
+        let mut shards = (0..4).into_deluge().split(2, SplitMode::RoundRobin);
+        let second = shards.pop().unwrap();
+        let first = shards.pop().unwrap();
+
+        // Looking for its own elements, the first shard queues the second one's
+        let taken = std::iter::from_fn(|| first.next()).collect::<Vec<_>>();
+        let taken = futures::future::join_all(taken).await;
+        assert_eq!(taken, vec![Some(0), Some(2)]);
+        assert!(first.next().is_none());
+
+        // The orphans don't bring a shard that ran out back
+        drop(second);
+        assert!(first.next().is_none());
+        assert_eq!(first.size_hint(), (0, Some(0)));
+    }
 
-        assert!(!result);
-        // We might evaluate a little bit more than we should have, but not much more
-        assert_lt!(Arc::try_unwrap(evaluated).unwrap().into_inner().len(), 5);
+    #[tokio::test]
+    async fn split_stops_asking_the_deluge_once_it_ran_out() {
+        for mode in [SplitMode::RoundRobin, SplitMode::NextFree] {
+            let mut shards = Blinking::new().split(2, mode);
+            let second = shards.pop().unwrap();
+            let first = shards.pop().unwrap();
+
+            let first = first.collect::<Vec<usize>>(None).await;
+            let second = second.collect::<Vec<usize>>(None).await;
+
+            assert_eq!(first, vec![1]);
+            assert_eq!(second, Vec::<usize>::new());
+        }
     }
 
     #[tokio::test]
-    async fn all_par_works() {
-        let result = [1, 2, 3, 4]
+    async fn split_shards_work_on_separate_tasks() {
+        let in_flight = Arc::new(std::sync::Mutex::new((0, 0)));
+        let values = (0..40).map(|x| (x, 0)).collect();
+
+        let local = tokio::task::LocalSet::new();
+        let start = Instant::now();
+        let tasks = delayed_source(values, in_flight.clone())
+            .split(4, SplitMode::NextFree)
+            .into_iter()
+            .map(|shard| local.spawn_local(shard.collect::<Vec<(u64, usize)>>(5)))
+            .collect::<Vec<_>>();
+        local.await;
+
+        let mut result = Vec::new();
+        for task in tasks {
+            let shard = task.await.unwrap();
+            assert!(!shard.is_empty());
+            result.extend(shard.into_iter().map(|(x, _)| x));
+        }
+        result.sort();
+
+        assert_eq!(result, (0..40).collect::<Vec<u64>>());
+        assert_eq!(in_flight.lock().unwrap().1, 20);
+        // Two rounds of twenty elements
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 100);
+        assert_lt!(iteration_took.as_millis(), 200);
+    }
+
+    #[tokio::test]
+    async fn split_by_key_keeps_each_key_on_one_shard() {
+        let in_flight = Arc::new(std::sync::Mutex::new((0, 0)));
+        let values = (0..30).map(|x| (x, (x % 5) as usize)).collect();
+        let shards = delayed_source(values, in_flight.clone())
+            .filter_map(|x| async move { (x.0 % 3 != 0).then_some(x) })
+            .split_by_key(3, |x| x.1);
+
+        let start = Instant::now();
+        let results = futures::future::join_all(
+            shards
+                .into_iter()
+                .map(|shard| shard.collect::<Vec<(u64, usize)>>(4)),
+        )
+        .await;
+
+        let mut keys = HashMap::new();
+        let mut all = Vec::new();
+        for (shard, result) in results.into_iter().enumerate() {
+            for (x, key) in result {
+                assert_eq!(*keys.entry(key).or_insert(shard), shard);
+                all.push(x);
+            }
+        }
+        all.sort();
+
+        assert_eq!(all, (0..30).filter(|x| x % 3 != 0).collect::<Vec<u64>>());
+        assert_gt!(in_flight.lock().unwrap().1, 4);
+        let iteration_took = Instant::now() - start;
+        assert_lt!(iteration_took.as_millis(), 400);
+    }
+
+    #[tokio::test]
+    async fn split_by_key_shards_survive_a_panicked_element() {
+        let mut shards = (0..10)
             .into_deluge()
-            .all_par(None, None, |x| async move { x < 5 })
-            .await;
+            .map(panic_on_odd)
+            .split_by_key(2, |x| *x);
+        let second = shards.pop().unwrap();
+        let first = shards.pop().unwrap();
+
+        let (first, second) = futures::join!(
+            first
+                .collect::<Vec<usize>>(None)
+                .on_panic(PanicPolicy::Drop),
+            second.collect::<Vec<usize>>(2).on_panic(PanicPolicy::Drop),
+        );
+        let mut all = first.into_iter().chain(second).collect::<Vec<_>>();
+        all.sort();
+
+        assert_eq!(all, vec![0, 2, 4, 6, 8]);
+    }
 
-        assert!(result);
+    // A binary tree with 15 elements numbered breadth first
+    fn tree_children(x: usize) -> Vec<usize> {
+        if x < 8 {
+            vec![x * 2, x * 2 + 1]
+        } else {
+            vec![]
+        }
     }
 
     #[tokio::test]
-    async fn all_par_short_circuits() {
-        let evaluated = Arc::new(Mutex::new(Vec::new()));
+    async fn crawl_respects_one_concurrency_limit() {
+        let in_flight = Arc::new(std::sync::Mutex::new((0, 0)));
+        let limiter = ConcurrencyLimiter::new(3);
 
-        let result = [1, 2, 3, 4, 5, 6, 7]
+        let start = Instant::now();
+        let mut result: Vec<usize> = [1]
             .into_deluge()
-            .all_par(2, 1, |x| {
-                let evaluated = evaluated.clone();
+            .crawl(&limiter, |x: usize| {
+                let in_flight = in_flight.clone();
                 async move {
                     {
-                        let mut evaluated = evaluated.lock().await;
-                        evaluated.push(x);
+                        let mut in_flight = in_flight.lock().unwrap();
+                        in_flight.0 += 1;
+                        in_flight.1 = std::cmp::max(in_flight.0, in_flight.1);
                     }
-                    tokio::time::sleep(Duration::from_millis(100 - 10 * x)).await;
-                    x < 3
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    in_flight.lock().unwrap().0 -= 1;
+                    (x, tree_children(x))
                 }
             })
             .await;
+        result.sort();
 
-        assert!(!result);
-        // We might evaluate a little bit more than we should have, but not much more
-        assert_lt!(Arc::try_unwrap(evaluated).unwrap().into_inner().len(), 7);
+        assert_eq!(result, (1..16).collect::<Vec<usize>>());
+        assert_eq!(in_flight.lock().unwrap().1, 3);
+        assert_eq!(limiter.available(), 3);
+        // Six rounds, as the first two levels can't fill up the limit
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 250);
+        assert_lt!(iteration_took.as_millis(), 450);
     }
 
     #[tokio::test]
-    async fn chain_works() {
-        let result = [1, 2, 3, 4]
+    async fn crawl_expands_in_the_requested_order() {
+        let expand = |x: usize| async move { (x, tree_children(x)) };
+
+        let breadth_first: Vec<usize> = [1].into_deluge().crawl(1, expand).await;
+        assert_eq!(breadth_first, (1..16).collect::<Vec<usize>>());
+
+        let depth_first: Vec<usize> = [1]
             .into_deluge()
-            .chain([5, 6, 7, 8].into_deluge())
-            .collect::<Vec<usize>>(None)
+            .crawl(1, expand)
+            .order(CrawlOrder::DepthFirst)
             .await;
-
-        assert_eq!(result.len(), 8);
-        assert_eq!(result, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(
+            depth_first,
+            vec![1, 2, 4, 8, 9, 5, 10, 11, 3, 6, 12, 13, 7, 14, 15]
+        );
     }
 
     #[tokio::test]
-    async fn count_works() {
-        let result = [1, 2, 3, 4].into_deluge().count();
+    async fn crawl_stops_at_seen_elements_and_the_max_depth() {
+        // Every element leads to the two following ones, going around in a cycle
+        let expand = |x: usize| async move { (x, [(x + 1) % 5, (x + 2) % 5]) };
 
-        assert_eq!(result, 4);
-    }
+        let deduped: Vec<usize> = (0..2).into_deluge().crawl(None, expand).dedup().await;
+        assert_eq!(deduped, vec![0, 1, 2, 3, 4]);
 
-    #[tokio::test]
-    async fn we_can_mult() {
-        let result = [1, 2, 3, 4]
+        let shallow: Vec<usize> = [0].into_deluge().crawl(None, expand).max_depth(2).await;
+        assert_eq!(shallow, vec![0, 1, 2, 2, 3, 3, 4]);
+
+        let by_key: Vec<usize> = [0]
             .into_deluge()
-            .map(|x| async move { x * 2 })
-            .collect::<Vec<usize>>(None)
+            .crawl(None, expand)
+            .dedup_by_key(|x| x % 2)
             .await;
-
-        assert_eq!(vec![2, 4, 6, 8], result);
+        assert_eq!(by_key, vec![0, 1]);
     }
 
     #[tokio::test]
-    async fn filter_map_works() {
-        let result = [1, 2, 3, 4]
+    async fn crawl_moves_past_filtered_seeds() {
+        let expand = |x: usize| async move { (x, vec![]) };
+
+        let none: Vec<usize> = (0..4)
+            .into_deluge()
+            .filter_map(|_| async { None::<usize> })
+            .crawl(1, expand)
+            .await;
+        assert!(none.is_empty());
+
+        // The filtered out seed only resolves once the others are waiting on it
+        let some: Vec<usize> = (0..4)
             .into_deluge()
             .filter_map(|x| async move {
-                if x % 2 == 0 {
-                    Some("yes")
-                } else {
-                    None
+                if x == 1 {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
                 }
+                (x != 1).then_some(x)
             })
-            .collect::<Vec<&str>>(None)
+            .crawl(1, expand)
             .await;
-
-        assert_eq!(vec!["yes", "yes"], result);
+        assert_eq!(some, vec![0, 2, 3]);
     }
 
     #[tokio::test]
-    async fn we_can_go_between_values_and_deluges() {
-        let result = [1, 2, 3, 4]
-            .into_deluge()
-            .map(|x| async move { x * 2 })
-            .collect::<Vec<usize>>(None)
-            .await
+    async fn take_until_a_limit() {
+        let result = (0..100)
             .into_deluge()
-            .map(|x| async move { x * 2 })
-            .fold(None, 0, |acc, x| async move { acc + x })
+            .take(10)
+            .fold(None, 0, |acc, idx| async move { acc + idx })
             .await;
 
-        assert_eq!(result, 40);
+        assert_eq!(result, 45);
     }
 
+    #[cfg(feature = "async-runtime")]
     #[tokio::test]
-    async fn we_wait_cuncurrently() {
+    async fn timeout_drops_slow_elements() {
         let start = Instant::now();
-        let result = (0..100)
+        let result = (0..10)
             .into_deluge()
             .map(|idx| async move {
-                tokio::time::sleep(Duration::from_millis(100 - (idx as u64))).await;
+                if idx % 2 == 0 {
+                    futures::future::pending::<()>().await;
+                }
                 idx
             })
+            .timeout(Duration::from_millis(50), DropOnTimeout)
             .collect::<Vec<usize>>(None)
             .await;
 
         let iteration_took = Instant::now() - start;
-        assert_lt!(iteration_took.as_millis(), 200);
+        assert_lt!(iteration_took.as_millis(), 100);
 
-        assert_eq!(result.len(), 100);
-
-        result
-            .into_iter()
-            .enumerate()
-            .for_each(|(idx, elem)| assert_eq!(idx, elem));
+        assert_eq!(result, vec![1, 3, 5, 7, 9]);
     }
 
+    #[cfg(feature = "async-runtime")]
     #[tokio::test]
-    async fn concurrency_limit() {
-        let start = Instant::now();
-        let result = (0..15)
+    async fn timeout_errors_on_slow_elements() {
+        let result = (0..4)
             .into_deluge()
             .map(|idx| async move {
-                tokio::time::sleep(Duration::from_millis(50)).await;
+                tokio::time::sleep(Duration::from_millis(30 * idx)).await;
                 idx
             })
-            .collect::<Vec<usize>>(5)
+            .timeout(Duration::from_millis(50), ErrorOnTimeout)
+            .collect::<Vec<Result<u64, Elapsed>>>(1)
+            .await;
+
+        // The timer starts only once an element is picked up by the collector
+        assert_eq!(result[..2], [Ok(0), Ok(1)]);
+        assert!(result[2..].iter().all(Result::is_err));
+    }
+
+    #[cfg(feature = "async-runtime")]
+    #[tokio::test]
+    async fn rate_limit_spaces_out_elements() {
+        let start = Instant::now();
+        let result = (0..10)
+            .into_deluge()
+            .rate_limit(5, Duration::from_millis(100), None)
+            .map(|idx| async move { (idx, Instant::now()) })
+            .collect::<Vec<(usize, Instant)>>(None)
             .await;
 
         let iteration_took = Instant::now() - start;
-        assert_gt!(iteration_took.as_millis(), 150);
-        assert_lt!(iteration_took.as_millis(), 200);
+        assert_gt!(iteration_took.as_millis(), 95);
+        assert_lt!(iteration_took.as_millis(), 150);
+
+        for (idx, (elem, started_at)) in result.into_iter().enumerate() {
+            assert_eq!(idx, elem);
+            if idx < 5 {
+                assert_lt!((started_at - start).as_millis(), 10);
+            } else {
+                assert_gt!((started_at - start).as_millis(), (idx as u128 - 5) * 20);
+            }
+        }
+    }
 
-        assert_eq!(result.len(), 15);
+    #[cfg(feature = "async-runtime")]
+    #[tokio::test]
+    async fn rate_limit_gives_back_the_tokens_of_dropped_elements() {
+        let limited = (0..4)
+            .into_deluge()
+            .rate_limit(10, Duration::from_secs(1), 1);
+        let mut futures = std::iter::from_fn(|| limited.next()).collect::<Vec<_>>();
+        let (fourth, third) = (futures.pop().unwrap(), futures.pop().unwrap());
+
+        // The second element takes the first one's token as well, then waits for its own
+        let start = Instant::now();
+        let mut second = Box::pin(futures.pop().unwrap());
+        assert!(futures::poll!(second.as_mut()).is_pending());
+        drop(second);
+        drop(futures);
+
+        let third = third.await;
+        assert_lt!(start.elapsed().as_millis(), 50);
+        let fourth = fourth.await;
+        assert_gt!(start.elapsed().as_millis(), 95);
+        assert_lt!(start.elapsed().as_millis(), 150);
+        assert_eq!((third, fourth), (Some(2), Some(3)));
     }
 
+    #[cfg(feature = "async-runtime")]
     #[tokio::test]
-    async fn take_until_a_limit() {
-        let result = (0..100)
+    async fn rate_limit_is_shared_between_workers() {
+        let start = Instant::now();
+        let result = (0..20)
             .into_deluge()
-            .take(10)
-            .fold(None, 0, |acc, idx| async move { acc + idx })
+            .rate_limit(10, Duration::from_millis(100), 1)
+            .collect_par::<Vec<usize>>(4, None)
             .await;
 
-        assert_eq!(result, 45);
+        let iteration_took = Instant::now() - start;
+        assert_gt!(iteration_took.as_millis(), 185);
+        assert_lt!(iteration_took.as_millis(), 250);
+
+        assert_eq!(result.len(), 20);
+    }
+
+    #[cfg(feature = "async-runtime")]
+    #[tokio::test]
+    async fn rate_limit_spaces_out_elements_waiting_for_a_worker() {
+        let mut started = (0..6)
+            .into_deluge()
+            .rate_limit(5, Duration::from_millis(100), 1)
+            .map(|idx| async move {
+                let started = Instant::now();
+                // The two elements being evaluated finish together
+                tokio::time::sleep(Duration::from_millis(60 - 20 * (idx as u64 % 2))).await;
+                started
+            })
+            .collect_par::<Vec<Instant>>(2, 1)
+            .await;
+        started.sort();
+
+        // The elements loaded up front only take their tokens once a worker gets to them
+        for pair in started.windows(2) {
+            assert_gt!((pair[1] - pair[0]).as_millis(), 15);
+        }
     }
 
     #[tokio::test]
@@ -873,7 +3746,7 @@ mod tests {
 
     #[cfg(feature = "async-std")]
     #[async_std::test]
-    async fn parallel_test() {
+    async fn parallel_test_on_async_std() {
         let start = Instant::now();
         let result = (0..150)
             .into_deluge()
@@ -891,6 +3764,28 @@ mod tests {
         assert_eq!(result.len(), 150);
     }
 
+    #[cfg(feature = "smol")]
+    #[test]
+    fn parallel_test_on_smol() {
+        smol::block_on(async {
+            let start = Instant::now();
+            let result = (0..150)
+                .into_deluge()
+                .map(|idx| async move {
+                    smol::Timer::after(Duration::from_millis(50)).await;
+                    idx
+                })
+                .collect_par::<Vec<usize>>(10, 5)
+                .await;
+
+            let iteration_took = Instant::now() - start;
+            assert_gt!(iteration_took.as_millis(), 150);
+            assert_lt!(iteration_took.as_millis(), 200);
+
+            assert_eq!(result.len(), 150);
+        });
+    }
+
     #[cfg(feature = "tokio")]
     #[tokio::test]
     async fn parallel_fold() {
@@ -913,7 +3808,7 @@ mod tests {
 
     #[cfg(feature = "async-std")]
     #[async_std::test]
-    async fn parallel_fold() {
+    async fn parallel_fold_on_async_std() {
         let start = Instant::now();
         let result = (0..150)
             .into_deluge()
@@ -931,7 +3826,28 @@ mod tests {
         assert_eq!(result, 11175);
     }
 
-    #[cfg(feature = "async-runtime")]
+    #[cfg(feature = "smol")]
+    #[test]
+    fn parallel_fold_on_smol() {
+        smol::block_on(async {
+            let start = Instant::now();
+            let result = (0..150)
+                .into_deluge()
+                .map(|idx| async move {
+                    smol::Timer::after(Duration::from_millis(50)).await;
+                    idx
+                })
+                .fold_par(10, 5, 0, |acc, x| async move { acc + x })
+                .await;
+
+            let iteration_took = Instant::now() - start;
+            assert_gt!(iteration_took.as_millis(), 150);
+            assert_lt!(iteration_took.as_millis(), 200);
+
+            assert_eq!(result, 11175);
+        });
+    }
+
     #[tokio::test]
     async fn zips_work() {
         let result = (0..100)
@@ -943,7 +3859,6 @@ mod tests {
         assert_eq!(result.len(), 80);
     }
 
-    #[cfg(feature = "async-runtime")]
     #[tokio::test]
     async fn zips_inverted_waits() {
         let other_deluge = (0..90).into_deluge().map(|idx| async move {
@@ -953,7 +3868,8 @@ mod tests {
             idx
         });
 
-        let result = (0..100).rev()
+        let result = (0..100)
+            .rev()
             .into_deluge()
             .map(|idx| async move {
                 tokio::time::sleep(Duration::from_millis(idx)).await;
@@ -0,0 +1,23 @@
+use std::future::Future;
+
+/// An asynchronous function transforming a single element.
+///
+/// Naming the returned future through `F` rather than as a separate type parameter
+/// lets the futures of the operations outlive the same lifetimes as the operations themselves.
+pub trait ElementFn<Item> {
+    type Future: Future;
+
+    fn call(&self, item: Item) -> Self::Future;
+}
+
+impl<Item, F, Fut> ElementFn<Item> for F
+where
+    F: Fn(Item) -> Fut,
+    Fut: Future,
+{
+    type Future = Fut;
+
+    fn call(&self, item: Item) -> Fut {
+        self(item)
+    }
+}
@@ -1,3 +1,4 @@
+use futures::lock::{Mutex, OwnedMutexGuard};
 use futures::stream::{StreamExt, StreamFuture};
 use futures::Stream;
 use pin_project::pin_project;
@@ -8,16 +9,6 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll, Waker};
 
-#[cfg(feature = "tokio")]
-type Mutex<T> = tokio::sync::Mutex<T>;
-#[cfg(feature = "async-std")]
-type Mutex<T> = async_std::sync::Mutex<T>;
-
-#[cfg(feature = "tokio")]
-type OwnedMutexGuard<T> = tokio::sync::OwnedMutexGuard<T>;
-#[cfg(feature = "async-std")]
-type OwnedMutexGuard<T> = async_std::sync::MutexGuardArc<T>;
-
 /// Stream wrapper allowing it's users to subscribe to an element at a specific index
 /// through `IndexableStream::get_nth`.
 pub struct IndexableStream<'a, S: Stream + 'a> {
@@ -78,14 +69,7 @@ impl<'a, S: Stream + 'a> Future for GetNthElement<'a, S> {
         }
 
         if this.mutex_guard.is_none() {
-            #[cfg(feature = "tokio")]
-            {
-                *this.mutex_guard = Some(Box::pin(this.indexable.inner.clone().lock_owned()));
-            }
-            #[cfg(feature = "async-std")]
-            {
-                *this.mutex_guard = Some(Box::pin(this.indexable.inner.lock_arc()));
-            }
+            *this.mutex_guard = Some(Box::pin(this.indexable.inner.clone().lock_owned()));
         }
 
         let mut mutex_guard = this.mutex_guard.take().unwrap();
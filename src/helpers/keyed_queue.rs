@@ -0,0 +1,163 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+/// Keeps track of which element is allowed to run for a given key.
+///
+/// Elements are registered by their index in the deluge once their key becomes known.
+/// Keys can resolve out of order, so an element is only scheduled into its key queue
+/// after all the elements preceding it have resolved their keys as well.
+/// This guarantees that elements sharing a key run in the order of the input deluge.
+pub struct KeyedQueue<K> {
+    inner: Mutex<InnerKeyedQueue<K>>,
+}
+
+struct InnerKeyedQueue<K> {
+    // Keys that were resolved, but can't be scheduled yet
+    // as some of the preceding elements are still unresolved
+    resolved: BTreeMap<usize, Option<K>>,
+    scheduled_up_to: usize,
+    queues: HashMap<K, VecDeque<usize>>,
+    // Elements queued behind another one with the same key
+    parked: usize,
+    wakers: HashMap<usize, Waker>,
+}
+
+impl<K: Hash + Eq + Clone> KeyedQueue<K> {
+    pub fn new() -> Self {
+        Self {
+            inner: Mutex::new(InnerKeyedQueue {
+                resolved: BTreeMap::new(),
+                scheduled_up_to: 0,
+                queues: HashMap::new(),
+                parked: 0,
+                wakers: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn ticket(&self, idx: usize) -> Ticket<'_, K> {
+        Ticket {
+            queue: self,
+            idx,
+            state: TicketState::Unresolved,
+        }
+    }
+
+    fn resolve(&self, idx: usize, key: Option<K>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.resolved.insert(idx, key);
+
+        loop {
+            let next_idx = inner.scheduled_up_to;
+            let key = match inner.resolved.remove(&next_idx) {
+                Some(key) => key,
+                None => break,
+            };
+            inner.scheduled_up_to += 1;
+
+            if let Some(key) = key {
+                let queue = inner.queues.entry(key).or_default();
+                queue.push_back(next_idx);
+
+                if queue.len() == 1 {
+                    if let Some(waker) = inner.wakers.remove(&next_idx) {
+                        waker.wake();
+                    }
+                } else {
+                    inner.parked += 1;
+                }
+            }
+        }
+    }
+
+    /// How many elements are waiting for an earlier element with the same key to finish
+    pub fn parked(&self) -> usize {
+        self.inner.lock().unwrap().parked
+    }
+
+    fn poll_turn(&self, idx: usize, key: &K, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if idx < inner.scheduled_up_to
+            && inner.queues.get(key).and_then(|queue| queue.front()) == Some(&idx)
+        {
+            inner.wakers.remove(&idx);
+            Poll::Ready(())
+        } else {
+            inner.wakers.insert(idx, cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    fn release(&self, idx: usize, key: &K) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.wakers.remove(&idx);
+
+        let (next_in_line, unparked) = if let Some(queue) = inner.queues.get_mut(key) {
+            // An element might release the key before it reaches the front
+            // of the queue if it's dropped while waiting for its turn
+            let queued_before = queue.len();
+            queue.retain(|queued| *queued != idx);
+            // Either the next element moves to the front or a parked one left the queue
+            let unparked = queued_before > 1 && queue.len() < queued_before;
+            if queue.is_empty() {
+                inner.queues.remove(key);
+                (None, unparked)
+            } else {
+                (queue.front().copied(), unparked)
+            }
+        } else {
+            (None, false)
+        };
+        if unparked {
+            inner.parked -= 1;
+        }
+
+        if let Some(waker) = next_in_line.and_then(|next| inner.wakers.remove(&next)) {
+            waker.wake();
+        }
+    }
+}
+
+enum TicketState<K> {
+    Unresolved,
+    Queued(K),
+    Released,
+}
+
+/// A place of a single element in the `KeyedQueue`.
+/// Releases the key for the following elements when dropped.
+pub struct Ticket<'a, K: Hash + Eq + Clone> {
+    queue: &'a KeyedQueue<K>,
+    idx: usize,
+    state: TicketState<K>,
+}
+
+impl<'a, K: Hash + Eq + Clone> Ticket<'a, K> {
+    /// Puts the element in line behind the preceding elements with the same key
+    pub fn enqueue(&mut self, key: K) {
+        self.queue.resolve(self.idx, Some(key.clone()));
+        self.state = TicketState::Queued(key);
+    }
+
+    /// Resolves once all the preceding elements with the same key have finished
+    pub fn poll_turn(&self, cx: &mut Context<'_>) -> Poll<()> {
+        match &self.state {
+            TicketState::Queued(key) => self.queue.poll_turn(self.idx, key, cx),
+            _ => Poll::Ready(()),
+        }
+    }
+}
+
+impl<'a, K: Hash + Eq + Clone> Drop for Ticket<'a, K> {
+    fn drop(&mut self) {
+        match std::mem::replace(&mut self.state, TicketState::Released) {
+            // Elements that were filtered out don't hold any key,
+            // but they still need to be accounted for
+            TicketState::Unresolved => self.queue.resolve(self.idx, None),
+            TicketState::Queued(key) => self.queue.release(self.idx, &key),
+            TicketState::Released => {}
+        }
+    }
+}
@@ -1,2 +1,8 @@
+pub(crate) mod element_fn;
 pub(crate) mod indexable_stream;
+pub(crate) mod keyed_queue;
+pub(crate) mod output_slots;
+pub(crate) mod owned_deluge;
 pub(crate) mod preloaded_futures;
+#[cfg(feature = "async-runtime")]
+pub(crate) mod token_bucket;
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::task::{Context, Poll, Waker};
+
+/// Hands the elements produced by an operation over to the futures waiting for them.
+///
+/// Each future gets an index when it's issued and resolves to the element produced
+/// with that index, or to `None` if the operation finished before producing it.
+pub(crate) struct OutputSlots<T> {
+    issued: usize,
+    produced: usize,
+    finished: bool,
+    // Elements waiting for the future with their index to pick them up
+    ready: BTreeMap<usize, T>,
+    waiting: BTreeMap<usize, Waker>,
+}
+
+impl<T> OutputSlots<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            issued: 0,
+            produced: 0,
+            finished: false,
+            ready: BTreeMap::new(),
+            waiting: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn issue(&mut self) -> usize {
+        self.issued += 1;
+        self.issued - 1
+    }
+
+    pub(crate) fn issued(&self) -> usize {
+        self.issued
+    }
+
+    pub(crate) fn produced(&self) -> usize {
+        self.produced
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub(crate) fn push(&mut self, item: T) {
+        self.ready.insert(self.produced, item);
+        if let Some(waker) = self.waiting.remove(&self.produced) {
+            waker.wake();
+        }
+        self.produced += 1;
+    }
+
+    pub(crate) fn finish(&mut self) {
+        self.finished = true;
+        self.waiting.values().for_each(Waker::wake_by_ref);
+    }
+
+    pub(crate) fn poll_take(&mut self, index: usize, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if let Some(item) = self.ready.remove(&index) {
+            self.waiting.remove(&index);
+            Poll::Ready(Some(item))
+        } else if self.finished {
+            self.waiting.remove(&index);
+            Poll::Ready(None)
+        } else {
+            self.waiting.insert(index, cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// Called when the future with the `index` is dropped,
+    /// returns whether another future took over from it
+    pub(crate) fn release(&mut self, index: usize) -> bool {
+        self.waiting.remove(&index);
+        // The inputs might only know to wake the dropped future up,
+        // so another waiting one has to take over driving the operation
+        self.wake_one()
+    }
+
+    /// Wakes up one of the waiting futures, returning whether there was one
+    pub(crate) fn wake_one(&self) -> bool {
+        match self.waiting.values().next() {
+            Some(waker) => {
+                waker.wake_by_ref();
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ptr::NonNull;
+
+/// Names the type holding on to the borrows of a deluge that lives for `'x`,
+/// such as the futures it returned.
+///
+/// The last parameter is never set, it only tells the compiler that `Del: 'x`,
+/// so the borrowing type can use `Del::Output<'x>`.
+pub(crate) trait Borrowers<'x, Del, Outlives = &'x Del> {
+    type Of;
+}
+
+/// Owns a deluge along with the values borrowing it.
+///
+/// The deluge is kept at a fixed address on the heap, so the borrowers can be stored
+/// right next to it and moved around with it. They are only reachable through
+/// `with` and `with_mut`, which hand them out for an anonymous lifetime `'x`.
+/// The compiler makes sure nothing borrowing the deluge leaves the closure
+/// other than through the borrowers, which are dropped before the deluge.
+pub(crate) struct OwnedDeluge<'a, Del, B>
+where
+    Del: 'a,
+    B: for<'x> Borrowers<'x, Del>,
+{
+    deluge: NonNull<Del>,
+    borrowers: ManuallyDrop<<B as Borrowers<'a, Del>>::Of>,
+    _owned: PhantomData<Del>,
+}
+
+impl<'a, Del, B> OwnedDeluge<'a, Del, B>
+where
+    Del: 'a,
+    B: for<'x> Borrowers<'x, Del>,
+{
+    pub(crate) fn new<F>(deluge: Del, borrowers: F) -> Self
+    where
+        F: for<'x> FnOnce(&'x Del) -> <B as Borrowers<'x, Del>>::Of,
+    {
+        let deluge = NonNull::from(Box::leak(Box::new(deluge)));
+        // SAFETY: the deluge was just leaked and is only freed in `drop`
+        let borrowers = borrowers(unsafe { deluge.as_ref() });
+
+        Self {
+            deluge,
+            borrowers: ManuallyDrop::new(borrowers),
+            _owned: PhantomData,
+        }
+    }
+
+    pub(crate) fn with<F, R>(&self, f: F) -> R
+    where
+        F: for<'x, 'y> FnOnce(&'x Del, &'y <B as Borrowers<'x, Del>>::Of) -> R,
+    {
+        // SAFETY: the deluge is only ever borrowed immutably
+        // and it outlives the borrowers, which are the only place a borrow can end up in
+        f(unsafe { self.deluge.as_ref() }, &self.borrowers)
+    }
+
+    pub(crate) fn with_mut<F, R>(&mut self, f: F) -> R
+    where
+        F: for<'x, 'y> FnOnce(&'x Del, &'y mut <B as Borrowers<'x, Del>>::Of) -> R,
+    {
+        // SAFETY: as in `with`, the borrowers are mutable but the deluge isn't
+        f(unsafe { self.deluge.as_ref() }, &mut self.borrowers)
+    }
+}
+
+impl<'a, Del, B> Drop for OwnedDeluge<'a, Del, B>
+where
+    Del: 'a,
+    B: for<'x> Borrowers<'x, Del>,
+{
+    fn drop(&mut self) {
+        // SAFETY: the borrowers are never touched again. They go first,
+        // as they might still use the deluge while being dropped.
+        unsafe { ManuallyDrop::drop(&mut self.borrowers) };
+        // SAFETY: the pointer was leaked from a box in `new`
+        // and nothing refers to it anymore
+        drop(unsafe { Box::from_raw(self.deluge.as_ptr()) });
+    }
+}
+
+// SAFETY: the deluge is owned exclusively, just like in a `Box`
+unsafe impl<'a, Del, B> Send for OwnedDeluge<'a, Del, B>
+where
+    Del: Send + 'a,
+    B: for<'x> Borrowers<'x, Del>,
+    <B as Borrowers<'a, Del>>::Of: Send,
+{
+}
+
+// SAFETY: `with` only hands out shared references to the deluge and the borrowers
+unsafe impl<'a, Del, B> Sync for OwnedDeluge<'a, Del, B>
+where
+    Del: Sync + 'a,
+    B: for<'x> Borrowers<'x, Del>,
+    <B as Borrowers<'a, Del>>::Of: Sync,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct Refs;
+
+    impl<'x, T> Borrowers<'x, T> for Refs {
+        type Of = Vec<&'x T>;
+    }
+
+    // Reads the value it borrows when dropped
+    struct ReadOnDrop<'x>(&'x RefCell<Vec<usize>>, Rc<RefCell<Vec<usize>>>);
+
+    impl Drop for ReadOnDrop<'_> {
+        fn drop(&mut self) {
+            self.1.borrow_mut().extend(self.0.borrow().iter());
+        }
+    }
+
+    struct Readers;
+
+    impl<'x> Borrowers<'x, RefCell<Vec<usize>>> for Readers {
+        type Of = Vec<ReadOnDrop<'x>>;
+    }
+
+    #[test]
+    fn the_borrowers_move_with_the_deluge() {
+        let mut owned = OwnedDeluge::<_, Refs>::new(5_usize, |value| vec![value]);
+        owned.with_mut(|value, refs| refs.push(value));
+
+        let moved = [owned];
+        moved[0].with(|value, refs| {
+            assert_eq!(refs.len(), 2);
+            assert!(refs.iter().all(|borrowed| std::ptr::eq(*borrowed, value)));
+        });
+    }
+
+    #[test]
+    fn the_borrowers_are_dropped_before_the_deluge() {
+        let read = Rc::new(RefCell::new(Vec::new()));
+        let owned = OwnedDeluge::<_, Readers>::new(RefCell::new(vec![1, 2]), |values| {
+            vec![ReadOnDrop(values, read.clone())]
+        });
+        owned.with(|values, _| values.borrow_mut().push(3));
+
+        drop(owned);
+        assert_eq!(*read.borrow(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn the_deluge_is_dropped_with_the_owner() {
+        let counter = Rc::new(());
+        let owned = OwnedDeluge::<_, Refs>::new(counter.clone(), |_| Vec::new());
+        assert_eq!(Rc::strong_count(&counter), 2);
+
+        drop(owned);
+        assert_eq!(Rc::strong_count(&counter), 1);
+    }
+}
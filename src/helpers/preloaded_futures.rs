@@ -1,38 +1,59 @@
 use crate::deluge::Deluge;
+use crate::helpers::owned_deluge::{Borrowers, OwnedDeluge};
 
-use std::cell::RefCell;
-use std::collections::VecDeque;
 use std::future::Future;
-use std::marker::PhantomData;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+type Storage<'x, Del> = Mutex<Vec<Option<Pin<Box<<Del as Deluge>::Output<'x>>>>>>;
+
+struct Preloaded;
+
+impl<'x, Del: Deluge> Borrowers<'x, Del> for Preloaded {
+    type Of = Storage<'x, Del>;
+}
 
 pub struct PreloadedFutures<'a, Del>
 where
     Del: Deluge + 'a,
 {
-    storage: RefCell<VecDeque<Pin<Box<Del::Output<'a>>>>>,
-    _del: PhantomData<Del>,
+    // Index of the next future `next` hands out
+    taken: AtomicUsize,
+    preloaded: usize,
+    // The lower size hint of the deluge before it was drained
+    lower_bound: usize,
+    deluge: OwnedDeluge<'a, Del, Preloaded>,
 }
 
 impl<'a, Del> PreloadedFutures<'a, Del>
 where
     Del: Deluge + 'a,
 {
+    /// Pulls all the futures out of the `deluge` right away
     pub fn new(deluge: Del) -> Self {
-        let mut storage = VecDeque::new();
-        let deluge_borrow: &'a Del = unsafe { std::mem::transmute(&deluge) };
-        while let Some(v) = deluge_borrow.next() {
-            storage.push_back(Box::pin(v));
-        }
+        let lower_bound = deluge.size_hint().0;
+        let mut preloaded = 0;
+        let deluge = OwnedDeluge::new(deluge, |deluge| {
+            let mut storage = Vec::new();
+            while let Some(v) = deluge.next() {
+                storage.push(Some(Box::pin(v)));
+            }
+            preloaded = storage.len();
+            Mutex::new(storage)
+        });
 
         Self {
-            storage: RefCell::new(storage),
-            _del: PhantomData,
+            taken: AtomicUsize::new(0),
+            preloaded,
+            lower_bound,
+            deluge,
         }
     }
 
     pub fn len(&self) -> usize {
-        self.storage.borrow().len()
+        self.preloaded - self.taken.load(Ordering::Relaxed).min(self.preloaded)
     }
 }
 
@@ -41,11 +62,73 @@ where
     Del: Deluge + 'a,
 {
     type Item = Del::Item;
-    type Output<'x> = impl Future<Output = Option<Self::Item>> + 'x where Self: 'x;
+    type Output<'x>
+        = PreloadedFuture<'x, 'a, Del>
+    where
+        Self: 'x;
 
     fn next(&self) -> Option<Self::Output<'_>> {
-        let next_item = { self.storage.borrow_mut().pop_front() };
+        let index = self.taken.fetch_add(1, Ordering::Relaxed);
+        if index < self.preloaded {
+            Some(PreloadedFuture {
+                preloaded: self,
+                index,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        let popped = self.preloaded - len;
+        (self.lower_bound.saturating_sub(popped), Some(len))
+    }
+}
+
+/// One of the futures preloaded by `PreloadedFutures`,
+/// evaluated in place as it borrows the deluge owned there
+pub struct PreloadedFuture<'x, 'a, Del>
+where
+    Del: Deluge + 'a,
+{
+    preloaded: &'x PreloadedFutures<'a, Del>,
+    index: usize,
+}
+
+impl<'x, 'a, Del> Future for PreloadedFuture<'x, 'a, Del>
+where
+    Del: Deluge + 'a,
+{
+    type Output = Option<Del::Item>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let index = self.index;
+        self.preloaded.deluge.with(|_, storage| {
+            // The future is polled outside of the lock, so the others can be polled meanwhile
+            let mut future = storage.lock().unwrap()[index]
+                .take()
+                .expect("a preloaded future polled after it completed");
 
-        next_item.map(|el| async move { el.await })
+            let result = future.as_mut().poll(cx);
+            if result.is_pending() {
+                storage.lock().unwrap()[index] = Some(future);
+            }
+            result
+        })
+    }
+}
+
+impl<'x, 'a, Del> Drop for PreloadedFuture<'x, 'a, Del>
+where
+    Del: Deluge + 'a,
+{
+    fn drop(&mut self) {
+        let index = self.index;
+        self.preloaded.deluge.with(|_, storage| {
+            // Dropped outside of the lock, just like it's polled outside of it
+            let future = storage.lock().unwrap()[index].take();
+            drop(future);
+        });
     }
 }
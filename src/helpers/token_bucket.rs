@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+/// A token bucket that hands out reservations instead of rejecting callers.
+///
+/// Every call to `reserve` takes a token and returns the time
+/// from which the caller is allowed to use it. Waiting callers are served in the order
+/// they made their reservations, so no caller can be starved by the others.
+///
+/// The bucket doesn't lock anything itself, its owner is expected to do so.
+pub struct TokenBucket {
+    emission_interval: Duration,
+    burst_tolerance: Duration,
+    // The time at which the bucket would become full again, if no more tokens were taken
+    theoretical_arrival: Option<Instant>,
+}
+
+impl TokenBucket {
+    /// Counts above `u32::MAX` saturate, as a `Duration` can only be divided
+    /// and multiplied by a `u32`, which still leaves sub-nanosecond intervals.
+    pub fn new(permits: usize, interval: Duration, burst: usize) -> Self {
+        let saturating_u32 = |count: usize| u32::try_from(count.max(1)).unwrap_or(u32::MAX);
+        let emission_interval = interval / saturating_u32(permits);
+
+        Self {
+            emission_interval,
+            burst_tolerance: emission_interval.saturating_mul(saturating_u32(burst) - 1),
+            theoretical_arrival: None,
+        }
+    }
+
+    pub fn reserve(&mut self) -> Instant {
+        let now = Instant::now();
+
+        let arrival = match self.theoretical_arrival {
+            Some(arrival) if arrival > now => arrival,
+            _ => now,
+        };
+        self.theoretical_arrival = Some(arrival + self.emission_interval);
+
+        std::cmp::max(
+            now,
+            arrival.checked_sub(self.burst_tolerance).unwrap_or(now),
+        )
+    }
+
+    /// Puts back a token that was reserved, but ended up unused,
+    /// so the next reservation can have it
+    pub fn release(&mut self) {
+        if let Some(arrival) = self.theoretical_arrival.as_mut() {
+            *arrival = arrival
+                .checked_sub(self.emission_interval)
+                .unwrap_or(*arrival);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_is_available_immediately() {
+        let mut bucket = TokenBucket::new(10, Duration::from_secs(1), 3);
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(bucket.reserve() - start < Duration::from_millis(10));
+        }
+
+        let wait = bucket.reserve() - start;
+        assert!(wait >= Duration::from_millis(100));
+        assert!(wait < Duration::from_millis(110));
+
+        let wait = bucket.reserve() - start;
+        assert!(wait >= Duration::from_millis(200));
+        assert!(wait < Duration::from_millis(210));
+    }
+
+    #[test]
+    fn released_tokens_are_reserved_again() {
+        let mut bucket = TokenBucket::new(10, Duration::from_secs(1), 1);
+
+        let start = Instant::now();
+        bucket.reserve();
+        let unused = bucket.reserve() - start;
+        assert!(unused >= Duration::from_millis(100));
+        bucket.release();
+
+        let wait = bucket.reserve() - start;
+        assert!(wait >= Duration::from_millis(100));
+        assert!(wait < Duration::from_millis(110));
+    }
+
+    #[test]
+    fn huge_counts_saturate() {
+        let mut bucket = TokenBucket::new(usize::MAX, Duration::MAX, usize::MAX);
+
+        let start = Instant::now();
+        assert!(bucket.reserve() - start < Duration::from_millis(10));
+    }
+}
@@ -6,7 +6,7 @@ use crate::Deluge;
 /// can be converted into `Deluge`.
 pub trait IntoDeluge<T>
 where
-    T: Deluge
+    T: Deluge,
 {
     fn into_deluge(self) -> T
     where
@@ -16,8 +16,7 @@ where
 impl<T> IntoDeluge<Iter<T::IntoIter>> for T
 where
     T: IntoIterator,
-    <T as IntoIterator>::IntoIter: Send + 'static,
-    <T as IntoIterator>::Item: Send,
+    <T as IntoIterator>::IntoIter: 'static,
 {
     fn into_deluge(self) -> Iter<T::IntoIter> {
         iter(self)
@@ -26,7 +25,7 @@ where
 
 impl<T> IntoDeluge<T> for T
 where
-    T: Deluge
+    T: Deluge,
 {
     fn into_deluge(self) -> Self {
         self
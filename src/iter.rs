@@ -1,9 +1,11 @@
 use crate::deluge::Deluge;
 use std::cell::RefCell;
-use std::future::{self, Future};
+use std::future;
+use std::iter::Fuse;
 
 pub struct Iter<I> {
-    iter: RefCell<I>,
+    // Fused, so the deluge keeps returning `None` even if the iterator does not
+    iter: RefCell<Fuse<I>>,
 }
 
 impl<I> Unpin for Iter<I> {}
@@ -14,22 +16,25 @@ where
     I: IntoIterator,
 {
     Iter {
-        iter: RefCell::new(i.into_iter()),
+        iter: RefCell::new(i.into_iter().fuse()),
     }
 }
 
 impl<I> Deluge for Iter<I>
 where
-    I: Iterator + Send + 'static,
-    <I as Iterator>::Item: Send,
+    I: Iterator + 'static,
 {
     type Item = I::Item;
-    type Output<'a> = impl Future<Output = Option<Self::Item>> + 'a;
+    type Output<'a> = future::Ready<Option<Self::Item>>;
 
     fn next(&self) -> Option<Self::Output<'_>> {
         let item = { self.iter.borrow_mut().next() };
         item.map(|item| future::ready(Some(item)))
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.borrow().size_hint()
+    }
 }
 
 #[cfg(test)]
@@ -1,11 +1,3 @@
-#![feature(associated_type_defaults)]
-#![feature(type_alias_impl_trait)]
-#![feature(const_trait_impl)]
-#![feature(let_chains)]
-#![feature(stmt_expr_attributes)]
-#![feature(extend_one)]
-#![feature(impl_trait_in_assoc_type)]
-
 //! # Deluge is (not) a Stream
 //!
 //! Deluge implements parallel and concurrent stream operations while driving the underlying futures concurrently.
@@ -19,8 +11,9 @@
 //!
 //! ### Available features
 //!
-//! By default the library does not build the parallel collectors and folds.
-//! In order to enable them, please enable either the `tokio` or `async-std` feature.
+//! The operations that wait for time to pass or run blocking code, like `timeout`,
+//! `rate_limit` or `map_blocking`, need an async runtime.
+//! In order to enable them, please enable either the `tokio`, `async-std` or `smol` feature.
 //!
 //! ```toml
 //! deluge = { version = "0.1", features = ["tokio"] }
@@ -32,6 +25,21 @@
 //! deluge = { version = "0.1", features = ["async-std"] }
 //! ```
 //!
+//! or
+//!
+//! ```toml
+//! deluge = { version = "0.1", features = ["smol"] }
+//! ```
+//!
+//! Several of them can be enabled at the same time, in which case tokio is preferred,
+//! followed by async-std.
+//! Operations that need a runtime, like `timeout` or `rate_limit`, can be moved to
+//! a different one, including your own implementation of the `Runtime` trait,
+//! with their `with_runtime` method.
+//!
+//! The `rayon` feature adds a `RayonRuntime`, which runs the closures of `map_blocking`
+//! on the rayon thread pool instead of the blocking pool of the async runtime.
+//!
 //! ### Design decisions
 //!
 //! This is an opinionated library that puts ease of use and external simplicity at the forefront.
@@ -59,7 +67,6 @@
 //!
 //! The parallel collector spawns a number of workers.
 //! If a number of workers is not specified, it will default to the number of cpus, if the concurrency limit is not specified each worker will default to `total_futures_to_evaluate / number_of_workers`.
-//! Note that you need to enable either a `tokio` or `async-std` feature to support parallel collectors.
 //!
 //! ```
 //! use deluge::*;
@@ -81,14 +88,42 @@
 //! # });
 //! ```
 
+mod cancellation;
+mod concurrency;
 mod deluge;
 mod deluge_ext;
 mod helpers;
 mod into_deluge;
 mod iter;
 mod ops;
+mod runtime;
 
 pub use self::deluge::*;
+pub use cancellation::CancellationToken;
+pub use concurrency::{AdaptiveConcurrency, Concurrency, ConcurrencyLimiter};
 pub use deluge_ext::*;
 pub use into_deluge::*;
 pub use iter::*;
+pub use ops::boxed::{BoxDeluge, DynDeluge, LocalBoxDeluge, LocalDynDeluge};
+pub use ops::catch_unwind::{PanicInfo, PanicPolicy};
+#[cfg(feature = "async-runtime")]
+pub use ops::collect_until::DeadlineReport;
+pub use ops::crawl::CrawlOrder;
+pub use ops::either::Either;
+pub use ops::join_by_key::{FullJoin, InnerJoin, JoinKind, LeftJoin};
+#[cfg(feature = "async-runtime")]
+pub use ops::map_retry::RetryPolicy;
+pub use ops::split::SplitMode;
+#[cfg(feature = "async-runtime")]
+pub use ops::timeout::{DropOnTimeout, Elapsed, ErrorOnTimeout, TimeoutKind};
+#[cfg(feature = "async-std")]
+pub use runtime::AsyncStdRuntime;
+#[cfg(feature = "async-runtime")]
+pub use runtime::DefaultRuntime;
+#[cfg(all(feature = "rayon", feature = "async-runtime"))]
+pub use runtime::RayonRuntime;
+pub use runtime::Runtime;
+#[cfg(feature = "smol")]
+pub use runtime::SmolRuntime;
+#[cfg(feature = "tokio")]
+pub use runtime::TokioRuntime;
@@ -2,6 +2,7 @@ use pin_project::pin_project;
 
 use super::collect_par::CollectPar;
 use super::map::Map;
+use crate::concurrency::Concurrency;
 use crate::deluge::Deluge;
 use futures::task::{Context, Poll};
 use futures::Stream;
@@ -28,7 +29,7 @@ where
     pub(crate) fn new(
         deluge: Del,
         worker_count: impl Into<Option<usize>>,
-        worker_concurrency: impl Into<Option<usize>>,
+        worker_concurrency: impl Into<Concurrency>,
         f: F,
     ) -> Self {
         Self {
@@ -2,6 +2,7 @@ use pin_project::pin_project;
 
 use super::collect::Collect;
 use super::map::Map;
+use crate::concurrency::Concurrency;
 use crate::deluge::Deluge;
 use futures::task::{Context, Poll};
 use futures::Stream;
@@ -12,8 +13,8 @@ use std::pin::Pin;
 pub struct Any<'a, Del, Fut, F>
 where
     Del: Deluge + 'a,
-    F: Fn(Del::Item) -> Fut + Send + 'a,
-    Fut: Future<Output = bool> + Send,
+    F: Fn(Del::Item) -> Fut + 'a,
+    Fut: Future<Output = bool>,
 {
     #[pin]
     stream: Collect<'a, Map<Del, F>, ()>,
@@ -22,10 +23,10 @@ where
 impl<'a, Del, Fut, F> Any<'a, Del, Fut, F>
 where
     Del: Deluge + 'a,
-    F: Fn(Del::Item) -> Fut + Send + 'a,
-    Fut: Future<Output = bool> + Send,
+    F: Fn(Del::Item) -> Fut + 'a,
+    Fut: Future<Output = bool>,
 {
-    pub(crate) fn new(deluge: Del, concurrency: impl Into<Option<usize>>, f: F) -> Self {
+    pub(crate) fn new(deluge: Del, concurrency: impl Into<Concurrency>, f: F) -> Self {
         Self {
             stream: Collect::new(Map::new(deluge, f), concurrency),
         }
@@ -35,8 +36,8 @@ where
 impl<'a, Del, Fut, F> Future for Any<'a, Del, Fut, F>
 where
     Del: Deluge + 'a,
-    F: Fn(Del::Item) -> Fut + Send + 'a,
-    Fut: Future<Output = bool> + Send,
+    F: Fn(Del::Item) -> Fut + 'a,
+    Fut: Future<Output = bool>,
 {
     type Output = bool;
 
@@ -0,0 +1,163 @@
+use crate::deluge::Deluge;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+type BoxedOutput<'x, Item> = Pin<Box<dyn Future<Output = Option<Item>> + Send + 'x>>;
+type LocalBoxedOutput<'x, Item> = Pin<Box<dyn Future<Output = Option<Item>> + 'x>>;
+
+/// An object safe counterpart of `Deluge`, implemented for every deluge
+/// that can be sent to another thread along with its futures
+///
+/// The futures are boxed, but they still borrow the deluge that created them.
+/// The methods are named differently from the ones of `Deluge`,
+/// so having both traits in scope doesn't make the calls ambiguous.
+pub trait DynDeluge<Item>: Send {
+    fn dyn_next(&self) -> Option<BoxedOutput<'_, Item>>;
+
+    fn dyn_size_hint(&self) -> (usize, Option<usize>);
+
+    fn dyn_poll_ready(&self, cx: &mut Context<'_>) -> Poll<()>;
+
+    fn dyn_parked(&self) -> usize;
+}
+
+impl<Del> DynDeluge<Del::Item> for Del
+where
+    Del: Deluge + Send,
+    for<'x> Del::Output<'x>: Send,
+{
+    fn dyn_next(&self) -> Option<BoxedOutput<'_, Del::Item>> {
+        self.next()
+            .map(|future| Box::pin(future) as BoxedOutput<'_, Del::Item>)
+    }
+
+    fn dyn_size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint()
+    }
+
+    fn dyn_poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.poll_ready(cx)
+    }
+
+    fn dyn_parked(&self) -> usize {
+        self.parked()
+    }
+}
+
+/// Like `DynDeluge`, but implemented for every deluge,
+/// including the ones that have to stay on a single thread
+pub trait LocalDynDeluge<Item> {
+    fn local_dyn_next(&self) -> Option<LocalBoxedOutput<'_, Item>>;
+
+    fn local_dyn_size_hint(&self) -> (usize, Option<usize>);
+
+    fn local_dyn_poll_ready(&self, cx: &mut Context<'_>) -> Poll<()>;
+
+    fn local_dyn_parked(&self) -> usize;
+}
+
+impl<Del: Deluge> LocalDynDeluge<Del::Item> for Del {
+    fn local_dyn_next(&self) -> Option<LocalBoxedOutput<'_, Del::Item>> {
+        self.next()
+            .map(|future| Box::pin(future) as LocalBoxedOutput<'_, Del::Item>)
+    }
+
+    fn local_dyn_size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint()
+    }
+
+    fn local_dyn_poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.poll_ready(cx)
+    }
+
+    fn local_dyn_parked(&self) -> usize {
+        self.parked()
+    }
+}
+
+/// A deluge with its type erased, which can be stored and passed around
+/// no matter which operations it was built from
+///
+/// Both the deluge and its futures can be sent to other threads,
+/// `LocalBoxDeluge` is there for the ones that can't.
+pub struct BoxDeluge<'a, Item> {
+    deluge: Box<dyn DynDeluge<Item> + 'a>,
+}
+
+impl<'a, Item> BoxDeluge<'a, Item> {
+    pub fn new<Del>(deluge: Del) -> Self
+    where
+        Del: Deluge<Item = Item> + Send + 'a,
+        for<'x> Del::Output<'x>: Send,
+    {
+        Self {
+            deluge: Box::new(deluge),
+        }
+    }
+}
+
+impl<'a, Item> Deluge for BoxDeluge<'a, Item> {
+    type Item = Item;
+    type Output<'x>
+        = BoxedOutput<'x, Item>
+    where
+        Self: 'x;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.deluge.dyn_next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deluge.dyn_size_hint()
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.dyn_poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.dyn_parked()
+    }
+}
+
+/// A deluge with its type erased, like `BoxDeluge`,
+/// which doesn't need to be sent to other threads
+pub struct LocalBoxDeluge<'a, Item> {
+    deluge: Box<dyn LocalDynDeluge<Item> + 'a>,
+}
+
+impl<'a, Item> LocalBoxDeluge<'a, Item> {
+    pub fn new<Del>(deluge: Del) -> Self
+    where
+        Del: Deluge<Item = Item> + 'a,
+    {
+        Self {
+            deluge: Box::new(deluge),
+        }
+    }
+}
+
+impl<'a, Item> Deluge for LocalBoxDeluge<'a, Item> {
+    type Item = Item;
+    type Output<'x>
+        = LocalBoxedOutput<'x, Item>
+    where
+        Self: 'x;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.deluge.local_dyn_next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deluge.local_dyn_size_hint()
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.local_dyn_poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.local_dyn_parked()
+    }
+}
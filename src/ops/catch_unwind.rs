@@ -0,0 +1,142 @@
+use crate::deluge::Deluge;
+use pin_project::pin_project;
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pub(crate) type PanicPayload = Box<dyn Any + Send + 'static>;
+
+/// Decides what a collector does when one of the elements panics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PanicPolicy {
+    /// The panic brings down the collector right away
+    #[default]
+    Propagate,
+    /// The panicked element is dropped, as if it was filtered out
+    Drop,
+    /// The panicked element is dropped and the other elements are evaluated.
+    /// The first panic is resumed once all of them were delivered.
+    Resume,
+}
+
+/// Describes a panic of a single element
+pub struct PanicInfo {
+    payload: PanicPayload,
+}
+
+impl PanicInfo {
+    pub(crate) fn new(payload: PanicPayload) -> Self {
+        Self { payload }
+    }
+
+    /// The message the element panicked with, if it was a string
+    pub fn message(&self) -> Option<&str> {
+        if let Some(message) = self.payload.downcast_ref::<&'static str>() {
+            Some(message)
+        } else {
+            self.payload.downcast_ref::<String>().map(String::as_str)
+        }
+    }
+
+    /// The value the element panicked with,
+    /// which can be passed to `std::panic::resume_unwind`
+    pub fn into_payload(self) -> Box<dyn Any + Send + 'static> {
+        self.payload
+    }
+}
+
+impl fmt::Debug for PanicInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PanicInfo")
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+impl fmt::Display for PanicInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message() {
+            Some(message) => write!(f, "deluge element panicked: {message}"),
+            None => write!(f, "deluge element panicked"),
+        }
+    }
+}
+
+impl std::error::Error for PanicInfo {}
+
+pub struct CatchUnwind<Del> {
+    deluge: Del,
+}
+
+impl<Del> CatchUnwind<Del> {
+    pub(crate) fn new(deluge: Del) -> Self {
+        Self { deluge }
+    }
+}
+
+impl<Del> Deluge for CatchUnwind<Del>
+where
+    Del: Deluge,
+{
+    type Item = Result<Del::Item, PanicInfo>;
+    type Output<'a>
+        = CatchUnwindFuture<'a, Del>
+    where
+        Self: 'a;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.deluge.next().map(|item| CatchUnwindFuture { item })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deluge.size_hint()
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
+}
+
+#[pin_project]
+pub struct CatchUnwindFuture<'a, Del: Deluge + 'a> {
+    #[pin]
+    item: Del::Output<'a>,
+}
+
+impl<'a, Del: Deluge + 'a> Future for CatchUnwindFuture<'a, Del> {
+    type Output = Option<Result<Del::Item, PanicInfo>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let item = self.project().item;
+        match std::panic::catch_unwind(AssertUnwindSafe(|| item.poll(cx))) {
+            Ok(poll) => poll.map(|item| item.map(Ok)),
+            Err(payload) => Poll::Ready(Some(Err(PanicInfo::new(payload)))),
+        }
+    }
+}
+
+/// Polls an element future, catching its panic unless the `policy` lets it through
+pub(crate) fn poll_catching<Fut>(
+    future: Pin<&mut Fut>,
+    policy: PanicPolicy,
+    cx: &mut Context<'_>,
+) -> Poll<Result<Fut::Output, PanicPayload>>
+where
+    Fut: Future + ?Sized,
+{
+    if policy == PanicPolicy::Propagate {
+        return future.poll(cx).map(Ok);
+    }
+
+    match std::panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+        Ok(poll) => poll.map(Ok),
+        Err(payload) => Poll::Ready(Err(payload)),
+    }
+}
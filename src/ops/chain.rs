@@ -2,53 +2,94 @@ use std::marker::PhantomData;
 use std::sync::Mutex;
 
 use crate::deluge::Deluge;
-use futures::Future;
+use futures::future::Either;
+use std::task::{Context, Poll};
 
 pub struct Chain<'a, Del1, Del2> {
     deluge1: Del1,
     deluge2: Del2,
-    first_exhausted: Mutex<bool>,
+    state: Mutex<ChainState>,
     _lifetime: PhantomData<&'a Del1>,
 }
 
 impl<'a, Del1, Del2> Chain<'a, Del1, Del2>
 where
     Del1: Deluge,
-    Del2: for<'x> Deluge<Item = Del1::Item, Output<'x> = Del1::Output<'x>>,
+    Del2: Deluge<Item = Del1::Item>,
 {
     pub(crate) fn new(deluge1: Del1, deluge2: Del2) -> Self {
         Self {
             deluge1,
             deluge2,
-            first_exhausted: Mutex::new(false),
+            state: Mutex::new(ChainState::First),
             _lifetime: PhantomData,
         }
     }
 }
 
+/// The deluge currently providing elements
+enum ChainState {
+    First,
+    Second,
+    Exhausted,
+}
+
 impl<'a, Del1, Del2> Deluge for Chain<'a, Del1, Del2>
 where
-    Del1: Deluge + 'static,
-    Del2: for<'x> Deluge<Item = Del1::Item, Output<'x> = Del1::Output<'x>> + 'static,
+    Del1: Deluge,
+    Del2: Deluge<Item = Del1::Item>,
 {
     type Item = Del1::Item;
-    type Output<'x> = impl Future<Output = Option<Self::Item>> + 'x where Self: 'x;
+    type Output<'x>
+        = Either<Del1::Output<'x>, Del2::Output<'x>>
+    where
+        Self: 'x;
 
     fn next(&self) -> Option<Self::Output<'_>> {
-        let mut first_exhausted = self.first_exhausted.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
 
-        let result = if *first_exhausted {
-            self.deluge2.next()
-        } else {
+        if let ChainState::First = *state {
             match self.deluge1.next() {
-                None => {
-                    *first_exhausted = true;
-                    self.deluge2.next()
-                }
-                otherwise => otherwise,
+                None => *state = ChainState::Second,
+                Some(item) => return Some(Either::Left(item)),
+            }
+        }
+
+        if let ChainState::Second = *state {
+            match self.deluge2.next() {
+                None => *state = ChainState::Exhausted,
+                Some(item) => return Some(Either::Right(item)),
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match *self.state.lock().unwrap() {
+            ChainState::First => {
+                let (lower1, upper1) = self.deluge1.size_hint();
+                let (lower2, upper2) = self.deluge2.size_hint();
+                let upper = match (upper1, upper2) {
+                    (Some(upper1), Some(upper2)) => upper1.checked_add(upper2),
+                    _ => None,
+                };
+                (lower1.saturating_add(lower2), upper)
             }
-        };
+            ChainState::Second => self.deluge2.size_hint(),
+            ChainState::Exhausted => (0, Some(0)),
+        }
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        match *self.state.lock().unwrap() {
+            ChainState::First => self.deluge1.poll_ready(cx),
+            ChainState::Second => self.deluge2.poll_ready(cx),
+            ChainState::Exhausted => Poll::Ready(()),
+        }
+    }
 
-        result.map(|v| async move { v.await })
+    fn parked(&self) -> usize {
+        self.deluge1.parked() + self.deluge2.parked()
     }
 }
@@ -1,4 +1,10 @@
+use crate::cancellation::{Cancellation, CancellationToken, Listener};
+use crate::concurrency::{Acquire, Concurrency, Permit};
 use crate::deluge::Deluge;
+use crate::helpers::owned_deluge::{Borrowers, OwnedDeluge};
+use crate::ops::catch_unwind::{poll_catching, PanicPayload, PanicPolicy};
+#[cfg(feature = "async-runtime")]
+use crate::ops::collect_until::DeadlineReport;
 use core::pin::Pin;
 use futures::stream::Stream;
 use futures::task::{Context, Poll};
@@ -7,44 +13,138 @@ use std::boxed::Box;
 use std::collections::{BTreeMap, HashMap};
 use std::default::Default;
 use std::future::Future;
-use std::num::NonZeroUsize;
 
 type DelOutput<'a, Del> = dyn Future<Output = Option<<Del as Deluge>::Item>> + 'a;
+// A future being evaluated, along with a permit from a shared limiter if one is used
+type InFlight<'a, Del> = (Pin<Box<DelOutput<'a, Del>>>, Option<Permit>);
+pub(crate) type BackoffWhen<'a, Item> = Box<dyn Fn(&Item) -> bool + Send + 'a>;
+
+// The size hint only decides how much room is made up front,
+// a huge deluge still gets its elements one allocation at a time
+const MAX_PREALLOCATED: usize = 1024;
+
+// The futures being evaluated, which borrow the deluge
+struct PolledFutures;
+
+impl<'x, Del: Deluge> Borrowers<'x, Del> for PolledFutures {
+    type Of = HashMap<usize, InFlight<'x, Del>>;
+}
 
 #[pin_project]
 pub struct Collect<'a, Del, C>
 where
-    Del: Deluge,
+    Del: Deluge + 'a,
 {
-    deluge: Del,
     deluge_exhausted: bool,
 
     insert_idx: usize,
-    concurrency: Option<NonZeroUsize>,
+    concurrency: Concurrency,
+    pending_permit: Option<Acquire>,
+    backoff_when: Option<BackoffWhen<'a, Del::Item>>,
+    cancellation: Option<Listener>,
+    panic_policy: PanicPolicy,
+    // The first panic, resumed once the remaining elements are delivered
+    panic: Option<PanicPayload>,
 
-    polled_futures: HashMap<usize, Pin<Box<DelOutput<'a, Del>>>>,
     completed_items: BTreeMap<usize, Option<Del::Item>>,
 
     last_provided_idx: Option<usize>,
     collection: Option<C>,
+
+    deluge: OwnedDeluge<'a, Del, PolledFutures>,
 }
 
-impl<'a, Del: Deluge, C: Default> Collect<'a, Del, C> {
-    pub(crate) fn new(deluge: Del, concurrency: impl Into<Option<usize>>) -> Self {
+impl<'a, Del: Deluge + 'a, C: Default> Collect<'a, Del, C> {
+    pub(crate) fn new(deluge: Del, concurrency: impl Into<Concurrency>) -> Self {
+        let concurrency = concurrency.into();
+        // At least this many elements get loaded at once, unless the limit stops us first
+        let in_flight = match (&concurrency, deluge.size_hint()) {
+            (Concurrency::Limited(limit), (lower, _)) => lower.min(limit.get()),
+            (Concurrency::Unlimited, (lower, Some(_))) => lower,
+            // A shared limiter or an endless deluge doesn't tell us much
+            _ => 0,
+        };
+
         Self {
-            deluge,
             deluge_exhausted: false,
 
             insert_idx: 0,
-            concurrency: concurrency.into().and_then(NonZeroUsize::new),
+            concurrency,
+            pending_permit: None,
+            backoff_when: None,
+            cancellation: None,
+            panic_policy: PanicPolicy::default(),
+            panic: None,
 
-            polled_futures: HashMap::new(),
             completed_items: BTreeMap::new(),
             last_provided_idx: None,
 
             collection: Some(C::default()),
+
+            deluge: OwnedDeluge::new(deluge, |_| {
+                HashMap::with_capacity(in_flight.min(MAX_PREALLOCATED))
+            }),
         }
     }
+
+    /// Treats the elements for which `f` returns true as failures,
+    /// making an `AdaptiveConcurrency` limit back off
+    pub fn backoff_when<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Del::Item) -> bool + Send + 'a,
+    {
+        self.backoff_when = Some(Box::new(f));
+        self
+    }
+
+    /// Allows the collection to be stopped early with the `token`
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancellation = Some(token.listen());
+        self
+    }
+
+    /// Decides what happens when one of the elements panics
+    pub fn on_panic(mut self, policy: PanicPolicy) -> Self {
+        self.panic_policy = policy;
+        self
+    }
+}
+
+impl<'a, Del: Deluge + 'a, C: Extend<Del::Item>> Collect<'a, Del, C> {
+    /// Stops the collection, returning everything that was evaluated so far
+    /// along with the indices of elements that were not
+    #[cfg(feature = "async-runtime")]
+    pub(crate) fn take_partial(self: Pin<&mut Self>) -> (C, DeadlineReport) {
+        let this = self.project();
+        let mut collection = this.collection.take().unwrap();
+
+        // Elements that completed after a missing one are still worth returning
+        let first_undelivered = this.last_provided_idx.map(|x| x + 1).unwrap_or(0);
+        collection.extend(
+            this.completed_items
+                .split_off(&first_undelivered)
+                .into_values()
+                .flatten(),
+        );
+
+        let mut in_flight = this.deluge.with_mut(|_, polled_futures| {
+            polled_futures
+                .drain()
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>()
+        });
+        in_flight.sort_unstable();
+        *this.pending_permit = None;
+
+        let not_started_from = if *this.deluge_exhausted {
+            None
+        } else {
+            Some(*this.insert_idx)
+        };
+        *this.deluge_exhausted = true;
+
+        (collection, DeadlineReport::new(in_flight, not_started_from))
+    }
 }
 
 impl<'a, Del, C> Stream for Collect<'a, Del, C>
@@ -57,53 +157,104 @@ where
         let this = self.as_mut().project();
 
         loop {
-            while !*this.deluge_exhausted {
-                let concurrency_limit = if let Some(limit) = this.concurrency {
-                    limit.get()
-                } else {
-                    usize::MAX
-                };
-
-                if this.polled_futures.len() < concurrency_limit {
-                    // We **know** that a reference to deluge lives for 'a,
-                    // so it should be safe to force the type system to acknowledge that
-                    let deluge: &'a Del = unsafe { std::mem::transmute(&mut *this.deluge) };
-                    let next = deluge.next();
-                    if let Some(future) = next {
-                        this.polled_futures
-                            .insert(*this.insert_idx, Box::pin(future));
+            let mut waiting = false;
+
+            match this
+                .cancellation
+                .as_ref()
+                .and_then(|listener| listener.poll(cx))
+            {
+                Some(Cancellation::Cancel) => {
+                    this.deluge
+                        .with_mut(|_, polled_futures| polled_futures.clear());
+                    this.completed_items.clear();
+                    *this.pending_permit = None;
+                    *this.deluge_exhausted = true;
+                    return Poll::Ready(None);
+                }
+                Some(Cancellation::Shutdown) => {
+                    // Nothing new gets pulled out of the deluge,
+                    // the elements already in flight are evaluated as usual
+                    *this.pending_permit = None;
+                    *this.deluge_exhausted = true;
+                }
+                None => (),
+            }
+
+            let completed_before = this.completed_items.len();
+            let mut parked_more = false;
+            this.deluge.with_mut(|deluge, polled_futures| {
+                let parked_before = deluge.parked();
+                while !*this.deluge_exhausted {
+                    // The deluge wakes us up once it knows more about its elements
+                    if deluge.poll_ready(cx).is_pending() {
+                        waiting = true;
+                        break;
+                    }
+
+                    // The parked elements are waiting for other ones to complete,
+                    // so they don't hold up the elements that can run meanwhile
+                    let in_flight = polled_futures.len().saturating_sub(deluge.parked());
+                    let permit =
+                        match poll_slot(this.concurrency, this.pending_permit, in_flight, cx) {
+                            Slot::Free(permit) => permit,
+                            Slot::Full => break,
+                            Slot::WaitingForPermit => {
+                                waiting = true;
+                                break;
+                            }
+                        };
+
+                    if let Some(future) = deluge.next() {
+                        polled_futures.insert(*this.insert_idx, (Box::pin(future), permit));
                         *this.insert_idx += 1;
                     } else {
                         *this.deluge_exhausted = true;
                     }
-                } else {
-                    // We would exceed the concurrency limit by loading more elements
-                    break;
                 }
-            }
 
-            // Drive all available futures
-            if !this.polled_futures.is_empty() {
-                this.polled_futures.retain(|idx, fut| {
-                    match Pin::new(fut).poll(cx) {
-                        Poll::Ready(v) => {
-                            // Drop the items that should be ignored on the floor.
-                            // The indexes in the `completed_items` map don't need
-                            // to be contignous, it's enough for them to be monotonic
-                            this.completed_items.insert(*idx, v);
-                            false
+                // Drive all available futures
+                if !polled_futures.is_empty() {
+                    polled_futures.retain(|idx, (fut, permit)| {
+                        match poll_catching(fut.as_mut(), *this.panic_policy, cx) {
+                            Poll::Ready(result) => {
+                                let panicked = result.is_err();
+                                // A panicked element is dropped just like a filtered out one
+                                let v = result.unwrap_or_else(|payload| {
+                                    if *this.panic_policy == PanicPolicy::Resume {
+                                        this.panic.get_or_insert(payload);
+                                    }
+                                    None
+                                });
+
+                                if let Some(permit) = permit.take() {
+                                    permit.complete(
+                                        panicked || is_failure(this.backoff_when.as_ref(), &v),
+                                    );
+                                }
+
+                                // Drop the items that should be ignored on the floor.
+                                // The indexes in the `completed_items` map don't need
+                                // to be contignous, it's enough for them to be monotonic
+                                this.completed_items.insert(*idx, v);
+                                false
+                            }
+                            _ => true,
                         }
-                        _ => true,
-                    }
-                });
-            }
+                    });
+                }
+                parked_more = deluge.parked() > parked_before;
+            });
 
-            // If all the polled futures were immediately evaluated
-            // and we can still evaluate something more, load more items to evaluate
+            // If some of the polled futures were evaluated, their slots can be
+            // filled up right away, even if an earlier element is still holding
+            // up the delivery, so load more items to evaluate
             //
             // Otherwise if these features need more time to evaluate
             // they will re-enter self::poll through the waker
-            if !this.polled_futures.is_empty() || *this.deluge_exhausted {
+            // Elements that got parked while being polled leave room for more as well
+            let made_progress = this.completed_items.len() > completed_before || parked_more;
+            if !made_progress || *this.deluge_exhausted || waiting {
                 break;
             }
         }
@@ -118,7 +269,14 @@ where
                 if val.is_some() {
                     return Poll::Ready(Some(val.take().unwrap()));
                 }
-            } else if this.polled_futures.is_empty() {
+            } else if *this.deluge_exhausted
+                && this
+                    .deluge
+                    .with(|_, polled_futures| polled_futures.is_empty())
+            {
+                if let Some(payload) = this.panic.take() {
+                    std::panic::resume_unwind(payload);
+                }
                 // Our input has been exhausted, nothing more to see
                 return Poll::Ready(None);
             } else {
@@ -138,7 +296,7 @@ where
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<C> {
         match self.as_mut().poll_next(cx) {
             Poll::Ready(Some(v)) => {
-                self.collection.as_mut().unwrap().extend_one(v);
+                self.collection.as_mut().unwrap().extend(Some(v));
                 Poll::Pending
             }
             Poll::Ready(None) => Poll::Ready(self.collection.take().unwrap()),
@@ -146,3 +304,44 @@ where
         }
     }
 }
+
+/// Whether a collector can start evaluating another element
+pub(crate) enum Slot {
+    /// Holding the permit from a shared limiter, if one is used
+    Free(Option<Permit>),
+    /// We would exceed the concurrency limit by loading more elements
+    Full,
+    /// The limiter will wake us up once a permit is available
+    WaitingForPermit,
+}
+
+/// Checks if a collector evaluating `in_flight` elements can start another one
+pub(crate) fn poll_slot(
+    concurrency: &Concurrency,
+    pending_permit: &mut Option<Acquire>,
+    in_flight: usize,
+    cx: &mut Context<'_>,
+) -> Slot {
+    match concurrency {
+        Concurrency::Unlimited => Slot::Free(None),
+        Concurrency::Limited(limit) if in_flight < limit.get() => Slot::Free(None),
+        Concurrency::Limited(_) => Slot::Full,
+        Concurrency::Shared(limiter) => {
+            let acquire = pending_permit.get_or_insert_with(|| limiter.acquire(1));
+            match Pin::new(acquire).poll(cx) {
+                Poll::Ready(permit) => {
+                    *pending_permit = None;
+                    Slot::Free(Some(permit))
+                }
+                Poll::Pending => Slot::WaitingForPermit,
+            }
+        }
+    }
+}
+
+pub(crate) fn is_failure<Item>(
+    backoff_when: Option<&BackoffWhen<'_, Item>>,
+    item: &Option<Item>,
+) -> bool {
+    matches!((backoff_when, item), (Some(backoff_when), Some(item)) if backoff_when(item))
+}
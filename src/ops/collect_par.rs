@@ -1,8 +1,15 @@
+use crate::cancellation::{Cancellation, CancellationToken, Listener};
+use crate::concurrency::{Concurrency, ConcurrencyLimiter, Permit};
 use crate::deluge::Deluge;
+use crate::helpers::owned_deluge::{Borrowers, OwnedDeluge};
+use crate::ops::catch_unwind::{poll_catching, PanicPayload, PanicPolicy};
+use crate::ops::collect::{is_failure, BackoffWhen};
 use core::pin::Pin;
+use futures::channel::mpsc::{self, SendError};
+use futures::lock::Mutex;
 use futures::stream::{FuturesUnordered, StreamExt};
 use futures::task::{Context, Poll};
-use futures::Stream;
+use futures::{SinkExt, Stream};
 use pin_project::pin_project;
 use std::boxed::Box;
 use std::collections::BTreeMap;
@@ -11,37 +18,39 @@ use std::future::Future;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 
-#[cfg(feature = "tokio")]
-type Mutex<T> = tokio::sync::Mutex<T>;
-#[cfg(feature = "tokio")]
-use tokio::sync::mpsc;
-#[cfg(feature = "tokio")]
-type SendError<T> = tokio::sync::mpsc::error::SendError<T>;
-
-#[cfg(feature = "async-std")]
-type Mutex<T> = async_std::sync::Mutex<T>;
-#[cfg(feature = "async-std")]
-use async_std::channel as mpsc;
-#[cfg(feature = "async-std")]
-type SendError<T> = mpsc::SendError<T>;
-
 type OutstandingFutures<'a, Del> =
     Arc<Mutex<BTreeMap<usize, Pin<Box<<Del as Deluge>::Output<'a>>>>>>;
-type CompletedItem<Del> = (usize, Option<<Del as Deluge>::Item>);
+// Along with the permit of the element and whether it panicked
+type CompletedItem<Del> = (usize, Option<<Del as Deluge>::Item>, Option<(Permit, bool)>);
 type Worker<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+// The first panic caught by any of the workers
+type CaughtPanic = Arc<std::sync::Mutex<Option<PanicPayload>>>;
+
+// The workers along with the futures waiting for them, all of which borrow the deluge
+struct Evaluation<'x, Del: Deluge + 'x> {
+    workers: Vec<Worker<'x>>,
+    outstanding_futures: Option<OutstandingFutures<'x, Del>>,
+}
+
+struct Workers;
+
+impl<'x, Del: Deluge> Borrowers<'x, Del> for Workers {
+    type Of = Evaluation<'x, Del>;
+}
 
 #[pin_project]
 pub struct CollectPar<'a, Del, C>
 where
     Del: Deluge + 'a,
 {
-    deluge: Del,
     deluge_exhausted: bool,
     worker_count: usize,
-    worker_concurrency: Option<NonZeroUsize>,
+    worker_concurrency: Concurrency,
+    backoff_when: Option<BackoffWhen<'a, Del::Item>>,
+    cancellation: Option<Listener>,
+    panic_policy: PanicPolicy,
+    panic: CaughtPanic,
 
-    workers: Vec<Worker<'a>>,
-    outstanding_futures: Option<OutstandingFutures<'a, Del>>,
     completed_items: BTreeMap<usize, Option<Del::Item>>,
     #[allow(clippy::type_complexity)]
     completed_channel: Option<(
@@ -51,34 +60,62 @@ where
 
     last_provided_idx: Option<usize>,
     collection: Option<C>,
+
+    deluge: OwnedDeluge<'a, Del, Workers>,
 }
 
-impl<'a, Del: Deluge, C: Default> CollectPar<'a, Del, C> {
+impl<'a, Del: Deluge + 'a, C: Default> CollectPar<'a, Del, C> {
     pub(crate) fn new(
         deluge: Del,
         worker_count: impl Into<Option<usize>>,
-        worker_concurrency: impl Into<Option<usize>>,
+        worker_concurrency: impl Into<Concurrency>,
     ) -> Self {
         let worker_count = worker_count.into().unwrap_or_else(num_cpus::get);
-        let mut workers = Vec::new();
-        workers.reserve_exact(worker_count);
 
         Self {
-            deluge,
             deluge_exhausted: false,
             worker_count,
-            worker_concurrency: worker_concurrency.into().and_then(NonZeroUsize::new),
+            worker_concurrency: worker_concurrency.into(),
+            backoff_when: None,
+            cancellation: None,
+            panic_policy: PanicPolicy::default(),
+            panic: CaughtPanic::default(),
 
-            workers,
-            outstanding_futures: None,
             completed_items: BTreeMap::new(),
             // Only spawn the channel after we know how many items we have to eval
             completed_channel: None,
 
             last_provided_idx: None,
             collection: Some(C::default()),
+
+            deluge: OwnedDeluge::new(deluge, |_| Evaluation {
+                workers: Vec::with_capacity(worker_count),
+                outstanding_futures: None,
+            }),
         }
     }
+
+    /// Treats the elements for which `f` returns true as failures,
+    /// making an `AdaptiveConcurrency` limit back off
+    pub fn backoff_when<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&Del::Item) -> bool + Send + 'a,
+    {
+        self.backoff_when = Some(Box::new(f));
+        self
+    }
+
+    /// Allows the collection to be stopped early with the `token`
+    pub fn with_cancellation(mut self, token: &CancellationToken) -> Self {
+        self.cancellation = Some(token.listen());
+        self
+    }
+
+    /// Decides what happens when one of the elements panics
+    pub fn on_panic(mut self, policy: PanicPolicy) -> Self {
+        self.panic_policy = policy;
+        self
+    }
 }
 
 // Approach
@@ -88,8 +125,11 @@ impl<'a, Del: Deluge, C: Default> CollectPar<'a, Del, C> {
 
 fn create_worker<'a, Del: Deluge + 'a>(
     outstanding_futures: OutstandingFutures<'a, Del>,
-    completed_channel: mpsc::Sender<CompletedItem<Del>>,
+    mut completed_channel: mpsc::Sender<CompletedItem<Del>>,
     concurrency: NonZeroUsize,
+    limiter: Option<ConcurrencyLimiter>,
+    panic_policy: PanicPolicy,
+    panic: CaughtPanic,
 ) -> Pin<Box<dyn Future<Output = ()> + 'a>> {
     Box::pin(async move {
         let mut evaluated_futures = FuturesUnordered::new();
@@ -99,12 +139,35 @@ fn create_worker<'a, Del: Deluge + 'a>(
             loop {
                 // Load up on work if we aren't full and we expect more work to show up
                 if evaluated_futures.len() < concurrency.get() && more_work_available {
+                    // With a shared limiter an idle worker waits for a permit,
+                    // while a busy one only takes the permits that are available right away
+                    let mut permit = match &limiter {
+                        Some(limiter) if evaluated_futures.is_empty() => {
+                            Some(limiter.acquire(1).await)
+                        }
+                        _ => None,
+                    };
+
                     let mut outstanding_futures = outstanding_futures.lock().await;
                     while evaluated_futures.len() < concurrency.get()
                         && !outstanding_futures.is_empty()
                     {
+                        if let Some(limiter) = &limiter {
+                            if permit.is_none() {
+                                permit = limiter.try_acquire(1);
+                            }
+                            if permit.is_none() {
+                                break;
+                            }
+                        }
+
                         if let Some((idx, fut)) = outstanding_futures.pop_first() {
-                            evaluated_futures.push(IndexedFuture::new(idx, fut));
+                            evaluated_futures.push(IndexedFuture::new(
+                                idx,
+                                fut,
+                                permit.take(),
+                                panic_policy,
+                            ));
                         }
                     }
 
@@ -113,8 +176,20 @@ fn create_worker<'a, Del: Deluge + 'a>(
                     }
                 }
 
-                if let Some(result) = evaluated_futures.next().await {
-                    completed_channel.send(result).await?;
+                if let Some((idx, result, permit)) = evaluated_futures.next().await {
+                    let panicked = result.is_err();
+                    // A panicked element is dropped just like a filtered out one
+                    let result = result.unwrap_or_else(|payload| {
+                        if panic_policy == PanicPolicy::Resume {
+                            panic.lock().unwrap().get_or_insert(payload);
+                        }
+                        None
+                    });
+
+                    // The permit is completed once the result is seen by the collector,
+                    // which knows which elements count as failures
+                    let permit = permit.map(|permit| (permit, panicked));
+                    completed_channel.send((idx, result, permit)).await?;
                 } else {
                     // If there is no more results to fetch, double check if nothing
                     // was returned into `outstanding_futures` by another crashing worker.
@@ -129,7 +204,7 @@ fn create_worker<'a, Del: Deluge + 'a>(
                 }
             }
 
-            Ok::<(), SendError<CompletedItem<Del>>>(())
+            Ok::<(), SendError>(())
         });
 
         if let Err(_e) = run_worker().await {
@@ -151,62 +226,100 @@ where
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.as_mut().project();
 
-        if !*this.deluge_exhausted {
-            let mut outstanding_futures = BTreeMap::new();
-            let mut insert_idx = 0;
+        let stopped = this.deluge.with_mut(|deluge, evaluation| {
+            match this
+                .cancellation
+                .as_ref()
+                .and_then(|listener| listener.poll(cx))
+            {
+                Some(Cancellation::Cancel) => {
+                    evaluation.workers.clear();
+                    evaluation.outstanding_futures = None;
+                    this.completed_items.clear();
+                    *this.deluge_exhausted = true;
+                    return true;
+                }
+                Some(Cancellation::Shutdown) => {
+                    if let Some(outstanding_futures) = evaluation.outstanding_futures.as_ref() {
+                        // Workers never hold the lock across an await point,
+                        // so it is always available while we're being polled.
+                        // Only the futures that no worker has started yet are dropped.
+                        if let Some(mut outstanding_futures) = outstanding_futures.try_lock() {
+                            outstanding_futures.clear();
+                        }
+                    } else {
+                        // Nothing was pulled out of the deluge yet
+                        *this.deluge_exhausted = true;
+                        return true;
+                    }
+                }
+                None => (),
+            }
 
-            // Load up all the futures
-            loop {
-                // We **know** that a reference to deluge lives for 'a,
-                // so it should be safe to force the dilesystem to acknowledge that
-                let deluge: &'a Del = unsafe { std::mem::transmute(&mut *this.deluge) };
+            if !*this.deluge_exhausted {
+                let mut outstanding_futures = BTreeMap::new();
+                let mut insert_idx = 0;
 
-                let next = deluge.next();
-                if let Some(future) = next {
+                // Load up all the futures
+                while let Some(future) = deluge.next() {
                     outstanding_futures.insert(insert_idx, Box::pin(future));
                     insert_idx += 1;
-                } else {
-                    *this.deluge_exhausted = true;
-                    break;
                 }
-            }
+                *this.deluge_exhausted = true;
 
-            let total_futures = outstanding_futures.len();
+                let total_futures = outstanding_futures.len();
 
-            #[cfg(feature = "tokio")]
-            {
                 *this.completed_channel = Some(mpsc::channel(total_futures));
-            }
-            #[cfg(feature = "async-std")]
-            {
-                *this.completed_channel = Some(mpsc::bounded(total_futures));
-            }
 
-            *this.outstanding_futures = Some(Arc::new(Mutex::new(outstanding_futures)));
-
-            // Spawn workers
-            if this.workers.is_empty() {
-                let worker_concurrency = this
-                    .worker_concurrency
-                    .or_else(|| NonZeroUsize::new(total_futures / *this.worker_count))
-                    .unwrap_or_else(|| unsafe { NonZeroUsize::new_unchecked(1) });
-
-                for _ in 0..(*this.worker_count) {
-                    this.workers.push(create_worker::<'a, Del>(
-                        this.outstanding_futures.as_ref().unwrap().clone(),
-                        this.completed_channel.as_ref().unwrap().0.clone(),
-                        worker_concurrency,
-                    ));
+                let outstanding_futures = Arc::new(Mutex::new(outstanding_futures));
+                evaluation.outstanding_futures = Some(outstanding_futures.clone());
+
+                // Spawn workers
+                if evaluation.workers.is_empty() {
+                    let default_concurrency = NonZeroUsize::new(total_futures / *this.worker_count)
+                        .unwrap_or(NonZeroUsize::MIN);
+                    let (worker_concurrency, limiter) = match this.worker_concurrency {
+                        Concurrency::Unlimited => (default_concurrency, None),
+                        Concurrency::Limited(limit) => (*limit, None),
+                        Concurrency::Shared(limiter) => {
+                            (default_concurrency, Some(limiter.clone()))
+                        }
+                    };
+
+                    for _ in 0..(*this.worker_count) {
+                        evaluation.workers.push(create_worker::<Del>(
+                            outstanding_futures.clone(),
+                            this.completed_channel.as_ref().unwrap().0.clone(),
+                            worker_concurrency,
+                            limiter.clone(),
+                            *this.panic_policy,
+                            this.panic.clone(),
+                        ));
+                    }
                 }
             }
-        }
 
-        // Drive the workers
-        this.workers
-            .retain_mut(|worker| !matches!(Pin::new(worker).poll(cx), Poll::Ready(_)));
+            // Drive the workers
+            evaluation
+                .workers
+                .retain_mut(|worker| !matches!(Pin::new(worker).poll(cx), Poll::Ready(_)));
+            false
+        });
+        if stopped {
+            return Poll::Ready(None);
+        }
 
         // Drain the compelted channel
-        while let Ok((idx, v)) = this.completed_channel.as_mut().unwrap().1.try_recv() {
+        while let Poll::Ready(Some((idx, v, permit))) = this
+            .completed_channel
+            .as_mut()
+            .unwrap()
+            .1
+            .poll_next_unpin(cx)
+        {
+            if let Some((permit, panicked)) = permit {
+                permit.complete(panicked || is_failure(this.backoff_when.as_ref(), &v));
+            }
             this.completed_items.insert(idx, v);
         }
 
@@ -220,9 +333,22 @@ where
                 if val.is_some() {
                     return Poll::Ready(Some(val.take().unwrap()));
                 }
-            } else if this.workers.is_empty() {
-                // Our input has been exhausted, nothing more to see
-                return Poll::Ready(None);
+            } else if this
+                .deluge
+                .with(|_, evaluation| evaluation.workers.is_empty())
+            {
+                // A shutdown might have left gaps in the indices, so skip over them
+                match this.completed_items.range(idx_to_provide..).next() {
+                    Some((idx, _)) => *this.last_provided_idx = Some(idx - 1),
+                    None => {
+                        let panic = this.panic.lock().unwrap().take();
+                        if let Some(payload) = panic {
+                            std::panic::resume_unwind(payload);
+                        }
+                        // Our input has been exhausted, nothing more to see
+                        return Poll::Ready(None);
+                    }
+                }
             } else {
                 return Poll::Pending;
             }
@@ -240,7 +366,7 @@ where
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<C> {
         match self.as_mut().poll_next(cx) {
             Poll::Ready(Some(v)) => {
-                self.collection.as_mut().unwrap().extend_one(v);
+                self.collection.as_mut().unwrap().extend(Some(v));
                 Poll::Pending
             }
             Poll::Ready(None) => Poll::Ready(self.collection.take().unwrap()),
@@ -251,14 +377,27 @@ where
 
 // A helper type allowing us to have a future with a synchronously available index on it
 #[pin_project]
-pub struct IndexedFuture<Fut> {
+pub(crate) struct IndexedFuture<Fut> {
     future: Pin<Box<Fut>>,
     index: usize,
+    // Handed back along with the result, so the worker can report how the evaluation went
+    permit: Option<Permit>,
+    panic_policy: PanicPolicy,
 }
 
 impl<Fut: Future> IndexedFuture<Fut> {
-    fn new(index: usize, future: Pin<Box<Fut>>) -> Self {
-        IndexedFuture { future, index }
+    fn new(
+        index: usize,
+        future: Pin<Box<Fut>>,
+        permit: Option<Permit>,
+        panic_policy: PanicPolicy,
+    ) -> Self {
+        IndexedFuture {
+            future,
+            index,
+            permit,
+            panic_policy,
+        }
     }
 
     fn index(&self) -> usize {
@@ -274,13 +413,13 @@ impl<Fut> Future for IndexedFuture<Fut>
 where
     Fut: Future,
 {
-    type Output = (usize, Fut::Output);
+    type Output = (usize, Result<Fut::Output, PanicPayload>, Option<Permit>);
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let this = self.as_mut().project();
 
-        match Pin::new(this.future).poll(cx) {
-            Poll::Ready(result) => Poll::Ready((*this.index, result)),
+        match poll_catching(this.future.as_mut(), *this.panic_policy, cx) {
+            Poll::Ready(result) => Poll::Ready((*this.index, result, this.permit.take())),
             _ => Poll::Pending,
         }
     }
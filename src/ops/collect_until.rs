@@ -0,0 +1,108 @@
+use crate::concurrency::Concurrency;
+use crate::deluge::Deluge;
+use crate::ops::collect::Collect;
+use crate::runtime::{DefaultRuntime, Runtime};
+use core::pin::Pin;
+use futures::task::{Context, Poll};
+use pin_project::pin_project;
+use std::future::Future;
+use std::time::Instant;
+
+/// Describes which elements did not make it into the collection before the deadline
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeadlineReport {
+    in_flight: Vec<usize>,
+    not_started_from: Option<usize>,
+}
+
+impl DeadlineReport {
+    pub(crate) fn new(in_flight: Vec<usize>, not_started_from: Option<usize>) -> Self {
+        Self {
+            in_flight,
+            not_started_from,
+        }
+    }
+
+    /// True if every element was evaluated before the deadline
+    pub fn is_complete(&self) -> bool {
+        self.in_flight.is_empty() && self.not_started_from.is_none()
+    }
+
+    /// Indices of the elements that were being evaluated when the deadline passed, in order
+    pub fn in_flight(&self) -> &[usize] {
+        &self.in_flight
+    }
+
+    /// The index of the first element that was never pulled out of the deluge.
+    /// None of the elements from this index onwards were started.
+    ///
+    /// The deluge is not drained to find out how many elements are left,
+    /// as it might be infinite.
+    pub fn not_started_from(&self) -> Option<usize> {
+        self.not_started_from
+    }
+}
+
+#[pin_project]
+pub struct CollectUntil<'a, Del, C, R = DefaultRuntime>
+where
+    Del: Deluge,
+    R: Runtime,
+{
+    #[pin]
+    collect: Collect<'a, Del, C>,
+    deadline: Instant,
+    // Only started on the first poll, as some runtimes need to be running to create a timer
+    timer: Option<Pin<Box<R::Sleep>>>,
+    runtime: R,
+}
+
+impl<'a, Del: Deluge, C: Default> CollectUntil<'a, Del, C> {
+    pub(crate) fn new(deluge: Del, concurrency: impl Into<Concurrency>, deadline: Instant) -> Self {
+        Self {
+            collect: Collect::new(deluge, concurrency),
+            deadline,
+            timer: None,
+            runtime: DefaultRuntime::default(),
+        }
+    }
+}
+
+impl<'a, Del: Deluge, C, R: Runtime> CollectUntil<'a, Del, C, R> {
+    /// Waits for the deadline with a different `runtime`
+    pub fn with_runtime<R2: Runtime>(self, runtime: R2) -> CollectUntil<'a, Del, C, R2> {
+        CollectUntil {
+            collect: self.collect,
+            deadline: self.deadline,
+            timer: None,
+            runtime,
+        }
+    }
+}
+
+impl<'a, Del, C, R> Future for CollectUntil<'a, Del, C, R>
+where
+    Del: Deluge + 'a,
+    C: Default + Extend<Del::Item>,
+    R: Runtime,
+{
+    type Output = (C, DeadlineReport);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        if let Poll::Ready(collection) = this.collect.as_mut().poll(cx) {
+            return Poll::Ready((collection, DeadlineReport::default()));
+        }
+
+        let time_left = this.deadline.saturating_duration_since(Instant::now());
+        let timer = this
+            .timer
+            .get_or_insert_with(|| Box::pin(this.runtime.sleep(time_left)));
+
+        match timer.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(this.collect.take_partial()),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
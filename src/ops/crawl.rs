@@ -0,0 +1,281 @@
+use crate::concurrency::{Acquire, Concurrency, Permit};
+use crate::deluge::Deluge;
+use crate::helpers::owned_deluge::{Borrowers, OwnedDeluge};
+use crate::ops::collect::{poll_slot, Slot};
+use core::pin::Pin;
+use futures::stream::Stream;
+use futures::task::{Context, Poll};
+use pin_project::pin_project;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::future::Future;
+use std::hash::Hash;
+
+// A future being evaluated, along with a permit from a shared limiter if one is used
+type InFlight<Fut> = (Pin<Box<Fut>>, Option<Permit>);
+// Returns whether an element is seen for the first time
+type FirstSeen<'a, Item> = Box<dyn FnMut(&Item) -> bool + Send + 'a>;
+
+// The seeds being evaluated, which borrow the deluge
+struct Seeds;
+
+impl<'x, Del: Deluge> Borrowers<'x, Del> for Seeds {
+    type Of = Vec<InFlight<Del::Output<'x>>>;
+}
+
+/// The order in which `crawl` starts expanding the elements it discovers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CrawlOrder {
+    /// The elements are expanded in the order they were discovered in,
+    /// so the shallower ones go first
+    #[default]
+    BreadthFirst,
+    /// The children of an element are expanded before the elements discovered earlier
+    DepthFirst,
+}
+
+#[pin_project(project = CrawlProj)]
+pub struct Crawl<'a, Del, F, Fut, Out, C>
+where
+    Del: Deluge + 'a,
+{
+    f: F,
+    concurrency: Concurrency,
+    pending_permit: Option<Acquire>,
+    order: CrawlOrder,
+    max_depth: Option<usize>,
+    first_seen: Option<FirstSeen<'a, Del::Item>>,
+
+    deluge_exhausted: bool,
+    // Elements waiting to be expanded, along with their depth
+    frontier: VecDeque<(Del::Item, usize)>,
+    // Along with the depth of the expanded element,
+    // ordered so the children are discovered in the order their parents started in
+    expanding: BTreeMap<usize, (InFlight<Fut>, usize)>,
+    completed_items: BTreeMap<usize, Out>,
+    insert_idx: usize,
+    provide_idx: usize,
+
+    collection: Option<C>,
+
+    // The seeds are evaluated before they can be expanded
+    deluge: OwnedDeluge<'a, Del, Seeds>,
+}
+
+impl<'a, Del, F, Fut, Out, C> Crawl<'a, Del, F, Fut, Out, C>
+where
+    Del: Deluge + 'a,
+    C: Default,
+{
+    pub(crate) fn new(deluge: Del, concurrency: impl Into<Concurrency>, f: F) -> Self {
+        Self {
+            f,
+            concurrency: concurrency.into(),
+            pending_permit: None,
+            order: CrawlOrder::default(),
+            max_depth: None,
+            first_seen: None,
+
+            deluge_exhausted: false,
+            frontier: VecDeque::new(),
+            expanding: BTreeMap::new(),
+            completed_items: BTreeMap::new(),
+            insert_idx: 0,
+            provide_idx: 0,
+
+            collection: Some(C::default()),
+
+            deluge: OwnedDeluge::new(deluge, |_| Vec::new()),
+        }
+    }
+
+    /// Sets the order in which the discovered elements are expanded,
+    /// which is also the order of the results
+    pub fn order(mut self, order: CrawlOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Stops discovering elements deeper than `depth`, with the seeds at depth 0
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Only expands the first of the elements with the same key returned by `key_fn`
+    pub fn dedup_by_key<K, KF>(mut self, key_fn: KF) -> Self
+    where
+        K: Hash + Eq + Send + 'a,
+        KF: Fn(&Del::Item) -> K + Send + 'a,
+    {
+        let mut seen = HashSet::new();
+        self.first_seen = Some(Box::new(move |item| seen.insert(key_fn(item))));
+        self
+    }
+
+    /// Only expands the first of the equal elements
+    pub fn dedup(self) -> Self
+    where
+        Del::Item: Hash + Eq + Clone + Send + 'a,
+    {
+        self.dedup_by_key(Del::Item::clone)
+    }
+}
+
+impl<'a, Del, F, Fut, Out, C> CrawlProj<'_, 'a, Del, F, Fut, Out, C>
+where
+    Del: Deluge + 'a,
+{
+    fn discover(&mut self, items: impl IntoIterator<Item = Del::Item>, depth: usize) {
+        if self.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            return;
+        }
+
+        let mut items = items
+            .into_iter()
+            .filter(|item| {
+                self.first_seen
+                    .as_mut()
+                    .is_none_or(|first_seen| first_seen(item))
+            })
+            .map(|item| (item, depth))
+            .collect::<Vec<_>>();
+
+        match self.order {
+            CrawlOrder::BreadthFirst => self.frontier.extend(items),
+            CrawlOrder::DepthFirst => {
+                // The first child goes first, before everything discovered earlier
+                items.reverse();
+                items
+                    .into_iter()
+                    .for_each(|item| self.frontier.push_front(item));
+            }
+        }
+    }
+}
+
+impl<'a, Del, F, Fut, Out, I, C> Stream for Crawl<'a, Del, F, Fut, Out, C>
+where
+    Del: Deluge + 'a,
+    F: Fn(Del::Item) -> Fut,
+    Fut: Future<Output = (Out, I)>,
+    I: IntoIterator<Item = Del::Item>,
+{
+    type Item = Out;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+
+        loop {
+            let (seeds, seeds_resolved, parked_more) = this.deluge.with_mut(|deluge, seeds| {
+                let parked_before = deluge.parked();
+                // The seeds are only pulled once there is nothing left to expand,
+                // which keeps the frontier from growing more than it has to
+                while !this.frontier.is_empty() || !*this.deluge_exhausted {
+                    // Parked seeds are waiting for other ones, so they don't take a slot
+                    let in_flight =
+                        seeds.len().saturating_sub(deluge.parked()) + this.expanding.len();
+                    let permit =
+                        match poll_slot(this.concurrency, this.pending_permit, in_flight, cx) {
+                            Slot::Free(permit) => permit,
+                            Slot::Full | Slot::WaitingForPermit => break,
+                        };
+
+                    if let Some((item, depth)) = this.frontier.pop_front() {
+                        let future = Box::pin((this.f)(item));
+                        this.expanding
+                            .insert(*this.insert_idx, ((future, permit), depth));
+                        *this.insert_idx += 1;
+                        continue;
+                    }
+
+                    // The deluge wakes us up once it knows more about its elements
+                    if deluge.poll_ready(cx).is_pending() {
+                        break;
+                    }
+                    match deluge.next() {
+                        Some(future) => seeds.push((Box::pin(future), permit)),
+                        None => *this.deluge_exhausted = true,
+                    }
+                }
+
+                let (mut resolved, mut resolved_count) = (Vec::new(), 0);
+                seeds.retain_mut(|(future, permit)| match future.as_mut().poll(cx) {
+                    Poll::Ready(item) => {
+                        resolved.extend(item);
+                        resolved_count += 1;
+                        if let Some(permit) = permit.take() {
+                            permit.complete(false);
+                        }
+                        false
+                    }
+                    Poll::Pending => true,
+                });
+                (resolved, resolved_count, deluge.parked() > parked_before)
+            });
+
+            let mut children = Vec::new();
+            this.expanding.retain(|idx, ((future, permit), depth)| {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready((output, items)) => {
+                        this.completed_items.insert(*idx, output);
+                        children.push((items, *depth + 1));
+                        if let Some(permit) = permit.take() {
+                            permit.complete(false);
+                        }
+                        false
+                    }
+                    Poll::Pending => true,
+                }
+            });
+
+            // Everything left is still being evaluated and knows to wake us up.
+            // A seed that was filtered out or parked still frees up room for the next one
+            if seeds_resolved == 0 && children.is_empty() && !parked_more {
+                break;
+            }
+            this.discover(seeds, 0);
+            // Depth first puts the children in front of the frontier,
+            // so the ones discovered last end up going first
+            if *this.order == CrawlOrder::DepthFirst {
+                children.reverse();
+            }
+            for (items, depth) in children {
+                this.discover(items, depth);
+            }
+        }
+
+        if let Some(output) = this.completed_items.remove(this.provide_idx) {
+            *this.provide_idx += 1;
+            Poll::Ready(Some(output))
+        } else if this.expanding.is_empty()
+            && this.deluge.with(|_, seeds| seeds.is_empty())
+            && this.frontier.is_empty()
+            && *this.deluge_exhausted
+        {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<'a, Del, F, Fut, Out, I, C> Future for Crawl<'a, Del, F, Fut, Out, C>
+where
+    Del: Deluge + 'a,
+    F: Fn(Del::Item) -> Fut,
+    Fut: Future<Output = (Out, I)>,
+    I: IntoIterator<Item = Del::Item>,
+    C: Default + Extend<Out>,
+{
+    type Output = C;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<C> {
+        loop {
+            match self.as_mut().poll_next(cx) {
+                Poll::Ready(Some(v)) => self.collection.as_mut().unwrap().extend(Some(v)),
+                Poll::Ready(None) => return Poll::Ready(self.collection.take().unwrap()),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::deluge::Deluge;
+use futures::future;
+use std::task::{Context, Poll};
+
+/// One of two deluges providing the same `Item`,
+/// so a pipeline can be built differently depending on a condition
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+impl<L, R> Deluge for Either<L, R>
+where
+    L: Deluge,
+    R: Deluge<Item = L::Item>,
+{
+    type Item = L::Item;
+    type Output<'x>
+        = future::Either<L::Output<'x>, R::Output<'x>>
+    where
+        Self: 'x;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        match self {
+            Either::Left(deluge) => deluge.next().map(future::Either::Left),
+            Either::Right(deluge) => deluge.next().map(future::Either::Right),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Either::Left(deluge) => deluge.size_hint(),
+            Either::Right(deluge) => deluge.size_hint(),
+        }
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        match self {
+            Either::Left(deluge) => deluge.poll_ready(cx),
+            Either::Right(deluge) => deluge.poll_ready(cx),
+        }
+    }
+
+    fn parked(&self) -> usize {
+        match self {
+            Either::Left(deluge) => deluge.parked(),
+            Either::Right(deluge) => deluge.parked(),
+        }
+    }
+}
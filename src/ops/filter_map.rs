@@ -1,5 +1,11 @@
 use crate::deluge::Deluge;
+use crate::helpers::element_fn::ElementFn;
+use crate::ops::map::{MapState, MapStateProj};
+use futures::ready;
+use pin_project::pin_project;
 use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
 pub struct FilterMap<Del, F> {
     deluge: Del,
@@ -15,21 +21,64 @@ impl<Del, F> FilterMap<Del, F> {
 impl<InputDel, Fut, F, FOutput> Deluge for FilterMap<InputDel, F>
 where
     InputDel: Deluge,
-    F: Fn(InputDel::Item) -> Fut + Send,
-    Fut: Future<Output = Option<FOutput>> + Send,
-    FOutput: Send,
+    F: Fn(InputDel::Item) -> Fut,
+    Fut: Future<Output = Option<FOutput>>,
 {
     type Item = FOutput;
-    type Output<'x> = impl Future<Output = Option<Self::Item>> + 'x where Self: 'x;
+    type Output<'x>
+        = FilterMapFuture<'x, InputDel, F>
+    where
+        Self: 'x;
 
     fn next(&self) -> Option<Self::Output<'_>> {
-        self.deluge.next().map(|item| async {
-            let item = item.await;
-            if let Some(item) = item {
-                (self.f)(item).await
-            } else {
-                None
-            }
+        self.deluge.next().map(|item| FilterMapFuture {
+            f: &self.f,
+            state: MapState::Input { item },
         })
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Any of the elements might get filtered out
+        (0, self.deluge.size_hint().1)
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
+}
+
+#[pin_project]
+pub struct FilterMapFuture<'a, Del: Deluge + 'a, F: ElementFn<Del::Item>> {
+    f: &'a F,
+    #[pin]
+    state: MapState<Del::Output<'a>, F::Future>,
+}
+
+impl<'a, Del, F, FOutput> Future for FilterMapFuture<'a, Del, F>
+where
+    Del: Deluge + 'a,
+    F: ElementFn<Del::Item>,
+    F::Future: Future<Output = Option<FOutput>>,
+{
+    type Output = Option<FOutput>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                MapStateProj::Input { item } => match ready!(item.poll(cx)) {
+                    Some(item) => {
+                        let future = this.f.call(item);
+                        this.state.set(MapState::Output { future });
+                    }
+                    None => return Poll::Ready(None),
+                },
+                MapStateProj::Output { future } => return future.poll(cx),
+            }
+        }
+    }
 }
@@ -1,5 +1,6 @@
 use crate::deluge::Deluge;
 use std::sync::Mutex;
+use std::task::{Context, Poll};
 
 pub struct First<Del> {
     deluge: Del,
@@ -20,7 +21,10 @@ where
     Del: Deluge,
 {
     type Item = Del::Item;
-    type Output<'a> = Del::Output<'a> where Self: 'a;
+    type Output<'a>
+        = Del::Output<'a>
+    where
+        Self: 'a;
 
     fn next(&self) -> Option<Self::Output<'_>> {
         let mut item_provided = self.item_provided.lock().unwrap();
@@ -32,4 +36,30 @@ where
             None
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if *self.item_provided.lock().unwrap() {
+            (0, Some(0))
+        } else {
+            // The first element might be a filtered out one, unless none of them are
+            let (lower, upper) = self.deluge.size_hint();
+            let exact = Some(lower) == upper;
+            (
+                (exact && lower > 0) as usize,
+                Some(upper.map_or(1, |upper| upper.min(1))),
+            )
+        }
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if *self.item_provided.lock().unwrap() {
+            Poll::Ready(())
+        } else {
+            self.deluge.poll_ready(cx)
+        }
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
 }
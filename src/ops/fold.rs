@@ -1,3 +1,4 @@
+use crate::concurrency::Concurrency;
 use crate::deluge::Deluge;
 use core::pin::Pin;
 use futures::task::{Context, Poll};
@@ -12,16 +13,17 @@ use super::collect::Collect;
 pub struct Fold<Del, Acc, F, Fut>
 where
     Del: Deluge,
-    F: FnMut(Acc, Del::Item) -> Fut + Send,
-    Fut: Future<Output = Acc> + Send,
+    F: FnMut(Acc, Del::Item) -> Fut,
+    Fut: Future<Output = Acc>,
 {
     deluge: Option<Del>,
     #[allow(clippy::type_complexity)]
     collect_future: Option<Pin<Box<dyn Future<Output = Vec<Del::Item>>>>>,
     collected_result: Option<std::vec::IntoIter<Del::Item>>,
+    #[pin]
     current_el_future: Option<Fut>,
 
-    concurrency: Option<usize>,
+    concurrency: Concurrency,
 
     acc: Option<Acc>,
     f: F,
@@ -31,10 +33,10 @@ where
 impl<Del, Acc, F, Fut> Fold<Del, Acc, F, Fut>
 where
     Del: Deluge,
-    F: FnMut(Acc, Del::Item) -> Fut + Send,
-    Fut: Future<Output = Acc> + Send,
+    F: FnMut(Acc, Del::Item) -> Fut,
+    Fut: Future<Output = Acc>,
 {
-    pub(crate) fn new(deluge: Del, concurrency: impl Into<Option<usize>>, acc: Acc, f: F) -> Self {
+    pub(crate) fn new(deluge: Del, concurrency: impl Into<Concurrency>, acc: Acc, f: F) -> Self {
         Self {
             deluge: Some(deluge),
             collect_future: None,
@@ -53,15 +55,16 @@ where
 impl<InputDel, Acc, F, Fut> Future for Fold<InputDel, Acc, F, Fut>
 where
     InputDel: Deluge + 'static,
-    F: FnMut(Acc, InputDel::Item) -> Fut + Send,
-    Fut: Future<Output = Acc> + Send,
+    F: FnMut(Acc, InputDel::Item) -> Fut,
+    Fut: Future<Output = Acc>,
 {
     type Output = Acc;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.as_mut().project();
+        let mut this = self.as_mut().project();
         if this.deluge.is_some() && this.collect_future.is_none() {
-            let collect_future = Collect::new(this.deluge.take().unwrap(), *this.concurrency);
+            let collect_future =
+                Collect::new(this.deluge.take().unwrap(), this.concurrency.clone());
             *this.collect_future = Some(Box::pin(collect_future));
         }
 
@@ -76,20 +79,22 @@ where
         }
 
         loop {
-            if let Some(collected_result) = this.collected_result.as_mut() && this.current_el_future.is_none() {
-                if let Some(el) = collected_result.next() {
-                    *this.current_el_future = Some((this.f)(this.acc.take().unwrap(), el));
-                } else {
-                    *this.collected_result = None;
-                    break;
+            if this.current_el_future.is_none() {
+                if let Some(collected_result) = this.collected_result.as_mut() {
+                    if let Some(el) = collected_result.next() {
+                        let future = (this.f)(this.acc.take().unwrap(), el);
+                        this.current_el_future.set(Some(future));
+                    } else {
+                        *this.collected_result = None;
+                        break;
+                    }
                 }
             }
 
-            if let Some(current_el_future) = this.current_el_future.as_mut() {
-                // We're manually pin-projecting since we need to project into an Option
-                match unsafe { Pin::new_unchecked(current_el_future) }.poll(cx) {
+            if let Some(current_el_future) = this.current_el_future.as_mut().as_pin_mut() {
+                match current_el_future.poll(cx) {
                     Poll::Ready(v) => {
-                        *this.current_el_future = None;
+                        this.current_el_future.set(None);
                         *this.acc = Some(v);
                     }
                     _ => return Poll::Pending,
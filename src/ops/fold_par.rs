@@ -1,3 +1,4 @@
+use crate::concurrency::Concurrency;
 use crate::deluge::Deluge;
 use core::pin::Pin;
 use futures::task::{Context, Poll};
@@ -19,10 +20,11 @@ where
     #[allow(clippy::type_complexity)]
     collect_future: Option<Pin<Box<dyn Future<Output = Vec<Del::Item>> + 'a>>>,
     collected_result: Option<std::vec::IntoIter<Del::Item>>,
+    #[pin]
     current_el_future: Option<Fut>,
 
     worker_count: Option<usize>,
-    worker_concurrency: Option<usize>,
+    worker_concurrency: Concurrency,
 
     acc: Option<Acc>,
     f: F,
@@ -38,7 +40,7 @@ where
     pub(crate) fn new(
         deluge: Del,
         worker_count: impl Into<Option<usize>>,
-        worker_concurrency: impl Into<Option<usize>>,
+        worker_concurrency: impl Into<Concurrency>,
         acc: Acc,
         f: F,
     ) -> Self {
@@ -67,12 +69,12 @@ where
     type Output = Acc;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.as_mut().project();
+        let mut this = self.as_mut().project();
         if this.deluge.is_some() && this.collect_future.is_none() {
             let collect_future = CollectPar::new(
                 this.deluge.take().unwrap(),
                 *this.worker_count,
-                *this.worker_concurrency,
+                this.worker_concurrency.clone(),
             );
             *this.collect_future = Some(Box::pin(collect_future));
         }
@@ -88,20 +90,22 @@ where
         }
 
         loop {
-            if let Some(collected_result) = this.collected_result.as_mut() && this.current_el_future.is_none() {
-                if let Some(el) = collected_result.next() {
-                    *this.current_el_future = Some((this.f)(this.acc.take().unwrap(), el));
-                } else {
-                    *this.collected_result = None;
-                    break;
+            if this.current_el_future.is_none() {
+                if let Some(collected_result) = this.collected_result.as_mut() {
+                    if let Some(el) = collected_result.next() {
+                        let future = (this.f)(this.acc.take().unwrap(), el);
+                        this.current_el_future.set(Some(future));
+                    } else {
+                        *this.collected_result = None;
+                        break;
+                    }
                 }
             }
 
-            if let Some(current_el_future) = this.current_el_future.as_mut() {
-                // We're manually pin-projecting since we need to project into an Option
-                match unsafe { Pin::new_unchecked(current_el_future) }.poll(cx) {
+            if let Some(current_el_future) = this.current_el_future.as_mut().as_pin_mut() {
+                match current_el_future.poll(cx) {
                     Poll::Ready(v) => {
-                        *this.current_el_future = None;
+                        this.current_el_future.set(None);
                         *this.acc = Some(v);
                     }
                     _ => return Poll::Pending,
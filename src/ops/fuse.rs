@@ -0,0 +1,59 @@
+use crate::deluge::Deluge;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+pub struct Fuse<Del> {
+    deluge: Del,
+    exhausted: Mutex<bool>,
+}
+
+impl<Del> Fuse<Del> {
+    pub(crate) fn new(deluge: Del) -> Self {
+        Self {
+            deluge,
+            exhausted: Mutex::new(false),
+        }
+    }
+}
+
+impl<Del> Deluge for Fuse<Del>
+where
+    Del: Deluge,
+{
+    type Item = Del::Item;
+    type Output<'a>
+        = Del::Output<'a>
+    where
+        Self: 'a;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        let mut exhausted = self.exhausted.lock().unwrap();
+        if *exhausted {
+            return None;
+        }
+
+        let next = self.deluge.next();
+        *exhausted = next.is_none();
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if *self.exhausted.lock().unwrap() {
+            (0, Some(0))
+        } else {
+            self.deluge.size_hint()
+        }
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if *self.exhausted.lock().unwrap() {
+            Poll::Ready(())
+        } else {
+            self.deluge.poll_ready(cx)
+        }
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
+}
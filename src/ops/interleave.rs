@@ -0,0 +1,105 @@
+use crate::deluge::Deluge;
+use futures::future::Either;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+pub struct Interleave<Del1, Del2> {
+    deluge1: Del1,
+    deluge2: Del2,
+    state: Mutex<InterleaveState>,
+}
+
+struct InterleaveState {
+    first_turn: bool,
+    first_exhausted: bool,
+    second_exhausted: bool,
+}
+
+impl<Del1, Del2> Interleave<Del1, Del2> {
+    pub(crate) fn new(deluge1: Del1, deluge2: Del2) -> Self {
+        Self {
+            deluge1,
+            deluge2,
+            state: Mutex::new(InterleaveState {
+                first_turn: true,
+                first_exhausted: false,
+                second_exhausted: false,
+            }),
+        }
+    }
+}
+
+impl<Del1, Del2> Deluge for Interleave<Del1, Del2>
+where
+    Del1: Deluge,
+    Del2: Deluge<Item = Del1::Item>,
+{
+    type Item = Del1::Item;
+    type Output<'x>
+        = Either<Del1::Output<'x>, Del2::Output<'x>>
+    where
+        Self: 'x;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        let mut state = self.state.lock().unwrap();
+
+        // Once one of the deluges runs out, the other one provides the rest
+        for _ in 0..2 {
+            let first_turn = state.first_turn;
+            state.first_turn = !first_turn;
+
+            if first_turn && !state.first_exhausted {
+                match self.deluge1.next() {
+                    Some(item) => return Some(Either::Left(item)),
+                    None => state.first_exhausted = true,
+                }
+            } else if !first_turn && !state.second_exhausted {
+                match self.deluge2.next() {
+                    Some(item) => return Some(Either::Right(item)),
+                    None => state.second_exhausted = true,
+                }
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let state = self.state.lock().unwrap();
+        let exhausted = (0, Some(0));
+        let (lower1, upper1) = if state.first_exhausted {
+            exhausted
+        } else {
+            self.deluge1.size_hint()
+        };
+        let (lower2, upper2) = if state.second_exhausted {
+            exhausted
+        } else {
+            self.deluge2.size_hint()
+        };
+
+        let upper = match (upper1, upper2) {
+            (Some(upper1), Some(upper2)) => upper1.checked_add(upper2),
+            _ => None,
+        };
+        (lower1.saturating_add(lower2), upper)
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let state = self.state.lock().unwrap();
+        // Only the deluge `next` is going to ask has to be ready
+        let first_next = !state.first_exhausted && (state.first_turn || state.second_exhausted);
+
+        if first_next {
+            self.deluge1.poll_ready(cx)
+        } else if !state.second_exhausted {
+            self.deluge2.poll_ready(cx)
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge1.parked() + self.deluge2.parked()
+    }
+}
@@ -0,0 +1,605 @@
+use crate::deluge::Deluge;
+use crate::helpers::output_slots::OutputSlots;
+use crate::helpers::owned_deluge::{Borrowers, OwnedDeluge};
+use crate::ops::catch_unwind::PanicPayload;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+/// Decides which elements end up in the result of a join and what they look like
+pub trait JoinKind<L, R> {
+    type Item;
+    /// Whether the left elements without a match are kept
+    const KEEP_LEFT: bool;
+    /// Whether the right elements without a match are kept
+    const KEEP_RIGHT: bool;
+
+    fn item(left: Option<L>, right: Option<R>) -> Self::Item;
+}
+
+/// Only the matching pairs of elements, as `(left, right)`
+pub struct InnerJoin;
+
+/// Every left element, as `(left, Some(right))` or `(left, None)` if nothing matched
+pub struct LeftJoin;
+
+/// Every element of both sides, with `None` in place of a missing match
+pub struct FullJoin;
+
+impl<L, R> JoinKind<L, R> for InnerJoin {
+    type Item = (L, R);
+    const KEEP_LEFT: bool = false;
+    const KEEP_RIGHT: bool = false;
+
+    fn item(left: Option<L>, right: Option<R>) -> Self::Item {
+        match (left, right) {
+            (Some(left), Some(right)) => (left, right),
+            _ => unreachable!("inner joins only keep the matching pairs"),
+        }
+    }
+}
+
+impl<L, R> JoinKind<L, R> for LeftJoin {
+    type Item = (L, Option<R>);
+    const KEEP_LEFT: bool = true;
+    const KEEP_RIGHT: bool = false;
+
+    fn item(left: Option<L>, right: Option<R>) -> Self::Item {
+        (left.expect("left joins keep every left element"), right)
+    }
+}
+
+impl<L, R> JoinKind<L, R> for FullJoin {
+    type Item = (Option<L>, Option<R>);
+    const KEEP_LEFT: bool = true;
+    const KEEP_RIGHT: bool = true;
+
+    fn item(left: Option<L>, right: Option<R>) -> Self::Item {
+        (left, right)
+    }
+}
+
+type Pair<Del1, Del2> = (
+    Option<<Del1 as Deluge>::Item>,
+    Option<<Del2 as Deluge>::Item>,
+);
+type Pairs<T, P> = Vec<(Option<T>, Option<P>)>;
+// A panicked element takes up an output of its own, so it's resumed by the future taking it
+type Joined<Del1, Del2> = Result<Pair<Del1, Del2>, PanicPayload>;
+
+pub struct JoinByKey<'a, Del1, Del2, K, KF1, KF2, J>
+where
+    Del1: Deluge + 'a,
+    Del2: Deluge + 'a,
+{
+    key_left: KF1,
+    key_right: KF2,
+    state: OwnedDeluge<'a, (Del1, Del2), State<K>>,
+    _kind: PhantomData<J>,
+}
+
+// The futures being evaluated borrow the deluges
+struct State<K>(PhantomData<K>);
+
+impl<'x, Del1: Deluge, Del2: Deluge, K> Borrowers<'x, (Del1, Del2)> for State<K> {
+    type Of = Mutex<JoinState<'x, Del1, Del2, K>>;
+}
+
+struct JoinState<'a, Del1: Deluge + 'a, Del2: Deluge + 'a, K> {
+    left: Side<'a, Del1>,
+    right: Side<'a, Del2>,
+    // The smaller side is loaded into a table, the other one probes it
+    table_left: bool,
+    left_table: Table<K, Del1::Item, Del2::Item>,
+    right_table: Table<K, Del2::Item, Del1::Item>,
+    joined: OutputSlots<Joined<Del1, Del2>>,
+    // Collectors waiting for more pairs to be known before asking for another future
+    ready_wakers: Vec<Waker>,
+}
+
+struct Side<'a, Del: Deluge + 'a> {
+    deluge: &'a Del,
+    in_flight: Vec<Pin<Box<Del::Output<'a>>>>,
+    pulled: usize,
+    exhausted: bool,
+}
+
+struct Table<K, T, P> {
+    groups: HashMap<K, Group<T>>,
+    // Probing elements that were evaluated before the table was complete
+    waiting: Vec<P>,
+    // Elements of either side that panicked while being evaluated
+    panicked: Vec<PanicPayload>,
+    // Kept up to date as the elements come in, so the bounds don't need to go over the groups
+    largest_group: usize,
+    unprobed: usize,
+}
+
+struct Group<Item> {
+    items: Vec<Item>,
+    probed: bool,
+}
+
+impl<Item> Default for Group<Item> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            probed: false,
+        }
+    }
+}
+
+impl<'a, Del1, Del2, K, KF1, KF2, J> JoinByKey<'a, Del1, Del2, K, KF1, KF2, J>
+where
+    Del1: Deluge + 'a,
+    Del2: Deluge + 'a,
+{
+    pub(crate) fn new(left: Del1, right: Del2, key_left: KF1, key_right: KF2) -> Self {
+        // Without an upper bound the right side is assumed to be the smaller one
+        let table_left = match (left.size_hint().1, right.size_hint().1) {
+            (Some(left), Some(right)) => left < right,
+            (Some(_), None) => true,
+            _ => false,
+        };
+
+        Self {
+            key_left,
+            key_right,
+            state: OwnedDeluge::new((left, right), |(left, right)| {
+                Mutex::new(JoinState {
+                    left: Side::new(left),
+                    right: Side::new(right),
+                    table_left,
+                    left_table: Table::new(),
+                    right_table: Table::new(),
+                    joined: OutputSlots::new(),
+                    ready_wakers: Vec::new(),
+                })
+            }),
+            _kind: PhantomData,
+        }
+    }
+}
+
+impl<'a, Del: Deluge + 'a> Side<'a, Del> {
+    fn new(deluge: &'a Del) -> Self {
+        Self {
+            deluge,
+            in_flight: Vec::new(),
+            pulled: 0,
+            exhausted: false,
+        }
+    }
+
+    fn pull(&mut self) -> bool {
+        if self.exhausted {
+            return false;
+        }
+
+        match self.deluge.next() {
+            Some(future) => {
+                self.in_flight.push(Box::pin(future));
+                self.pulled += 1;
+                true
+            }
+            None => {
+                self.exhausted = true;
+                false
+            }
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.exhausted && self.in_flight.is_empty()
+    }
+
+    /// Polls the elements being evaluated, returning how many of them resolved
+    /// and moving the ones that weren't filtered out into `ready`.
+    /// The panics are caught, as unwinding past the lock would poison it.
+    fn poll_ready(
+        &mut self,
+        ready: &mut Vec<Del::Item>,
+        panics: &mut Vec<PanicPayload>,
+        cx: &mut Context<'_>,
+    ) -> usize {
+        let in_flight_before = self.in_flight.len();
+        self.in_flight.retain_mut(|future| {
+            match std::panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                Ok(Poll::Ready(item)) => {
+                    ready.extend(item);
+                    false
+                }
+                Ok(Poll::Pending) => true,
+                Err(payload) => {
+                    panics.push(payload);
+                    false
+                }
+            }
+        });
+
+        in_flight_before - self.in_flight.len()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        if self.exhausted {
+            Some(self.pulled)
+        } else {
+            self.deluge.size_hint().1?.checked_add(self.pulled)
+        }
+    }
+}
+
+impl<K, T, P> Table<K, T, P> {
+    fn new() -> Self {
+        Self {
+            groups: HashMap::new(),
+            waiting: Vec::new(),
+            panicked: Vec::new(),
+            largest_group: 0,
+            unprobed: 0,
+        }
+    }
+
+    /// An upper bound of how many more pairs the elements pulled so far can produce
+    fn pending_pairs<'a, TDel, PDel>(
+        &self,
+        table_side: &Side<'a, TDel>,
+        probe_side: &Side<'a, PDel>,
+    ) -> usize
+    where
+        TDel: Deluge<Item = T> + 'a,
+        PDel: Deluge<Item = P> + 'a,
+    {
+        let unevaluated = table_side.in_flight.len();
+
+        // A probing element matches a whole group at most,
+        // which can still grow by the table elements being evaluated
+        let per_probe = (self.largest_group + unevaluated).max(1);
+        let probes = self.waiting.len() + probe_side.in_flight.len();
+        probes
+            .saturating_mul(per_probe)
+            .saturating_add(self.unprobed + unevaluated + self.panicked.len())
+    }
+
+    /// How many more pairs the elements pulled so far are likely to produce,
+    /// expecting each probing element to match a single table element
+    fn likely_pairs<'a, TDel, PDel>(
+        &self,
+        table_side: &Side<'a, TDel>,
+        probe_side: &Side<'a, PDel>,
+        keep_unmatched_table: bool,
+    ) -> usize
+    where
+        TDel: Deluge<Item = T> + 'a,
+        PDel: Deluge<Item = P> + 'a,
+    {
+        let probes = self.waiting.len() + probe_side.in_flight.len();
+        let unmatched_table = if keep_unmatched_table {
+            self.unprobed + table_side.in_flight.len()
+        } else {
+            0
+        };
+        probes + unmatched_table + self.panicked.len()
+    }
+}
+
+impl<K: Hash + Eq, T: Clone, P: Clone> Table<K, T, P> {
+    /// Evaluates the elements of both sides, returning the pairs `(table, probe)`
+    /// that can be produced so far, along with whether the join is complete.
+    ///
+    /// The table side is loaded whole, while the probing side only as far as
+    /// there are futures waiting for the `demand` more pairs.
+    fn step<'a, TDel, PDel>(
+        &mut self,
+        table_side: &mut Side<'a, TDel>,
+        probe_side: &mut Side<'a, PDel>,
+        demand: usize,
+        table_key: impl Fn(&T) -> K,
+        probe_key: impl Fn(&P) -> K,
+        cx: &mut Context<'_>,
+    ) -> (Pairs<T, P>, bool)
+    where
+        TDel: Deluge<Item = T> + 'a,
+        PDel: Deluge<Item = P> + 'a,
+    {
+        let mut pairs = Vec::new();
+        loop {
+            while table_side.pull() {}
+            while probe_side.in_flight.len() < demand.saturating_sub(pairs.len())
+                && probe_side.pull()
+            {}
+
+            let mut table_items = Vec::new();
+            let mut resolved = table_side.poll_ready(&mut table_items, &mut self.panicked, cx);
+            for item in table_items {
+                let group = self.groups.entry(table_key(&item)).or_default();
+                group.items.push(item);
+                self.largest_group = self.largest_group.max(group.items.len());
+                // Probing only starts once the whole table is loaded
+                self.unprobed += 1;
+            }
+            resolved += probe_side.poll_ready(&mut self.waiting, &mut self.panicked, cx);
+
+            if table_side.is_done() {
+                for item in self.waiting.drain(..) {
+                    let group = match self.groups.get_mut(&probe_key(&item)) {
+                        Some(group) => group,
+                        None => {
+                            pairs.push((None, Some(item)));
+                            continue;
+                        }
+                    };
+
+                    if !group.probed {
+                        group.probed = true;
+                        self.unprobed -= group.items.len();
+                    }
+                    for matched in group.items.iter() {
+                        pairs.push((Some(matched.clone()), Some(item.clone())));
+                    }
+                }
+
+                if probe_side.is_done() {
+                    for group in self.groups.drain().map(|(_, group)| group) {
+                        if !group.probed {
+                            pairs.extend(group.items.into_iter().map(|item| (Some(item), None)));
+                        }
+                    }
+                    self.unprobed = 0;
+                    return (pairs, true);
+                }
+            }
+
+            // Everything left is still being evaluated and knows to wake us up
+            if resolved == 0 {
+                return (pairs, false);
+            }
+        }
+    }
+}
+
+impl<'a, Del1: Deluge + 'a, Del2: Deluge + 'a, K> JoinState<'a, Del1, Del2, K> {
+    /// Joins elements until the pair at `index` is known
+    fn advance<J, KF1, KF2>(
+        &mut self,
+        index: usize,
+        key_left: &KF1,
+        key_right: &KF2,
+        cx: &mut Context<'_>,
+    ) where
+        Del1::Item: Clone,
+        Del2::Item: Clone,
+        K: Hash + Eq,
+        KF1: Fn(&Del1::Item) -> K,
+        KF2: Fn(&Del2::Item) -> K,
+        J: JoinKind<Del1::Item, Del2::Item>,
+    {
+        if self.joined.produced() > index || self.joined.is_finished() {
+            return;
+        }
+
+        let produced_before = self.joined.produced();
+        let demand = self.joined.issued() - produced_before;
+        let (pairs, done) = if self.table_left {
+            self.left_table.step(
+                &mut self.left,
+                &mut self.right,
+                demand,
+                key_left,
+                key_right,
+                cx,
+            )
+        } else {
+            let (pairs, done) = self.right_table.step(
+                &mut self.right,
+                &mut self.left,
+                demand,
+                key_right,
+                key_left,
+                cx,
+            );
+            let pairs = pairs
+                .into_iter()
+                .map(|(right, left)| (left, right))
+                .collect();
+            (pairs, done)
+        };
+
+        let panicked = if self.table_left {
+            &mut self.left_table.panicked
+        } else {
+            &mut self.right_table.panicked
+        };
+        for payload in panicked.drain(..) {
+            self.joined.push(Err(payload));
+        }
+        for pair in pairs {
+            let keep = match &pair {
+                (Some(_), Some(_)) => true,
+                (Some(_), None) => J::KEEP_LEFT,
+                (None, Some(_)) => J::KEEP_RIGHT,
+                (None, None) => false,
+            };
+            if keep {
+                self.joined.push(Ok(pair));
+            }
+        }
+        if done {
+            self.joined.finish();
+        }
+        if self.joined.produced() > produced_before || done {
+            self.ready_wakers.drain(..).for_each(Waker::wake);
+        }
+    }
+
+    /// An upper bound of how many pairs the join can produce from the elements pulled so far
+    fn possible_pairs(&self) -> usize {
+        let pending = if self.table_left {
+            self.left_table.pending_pairs(&self.left, &self.right)
+        } else {
+            self.right_table.pending_pairs(&self.right, &self.left)
+        };
+        self.joined.produced().saturating_add(pending)
+    }
+
+    /// How many pairs the join is likely to produce from the elements pulled so far
+    fn likely_pairs<J: JoinKind<Del1::Item, Del2::Item>>(&self) -> usize {
+        let pending = if self.table_left {
+            self.left_table
+                .likely_pairs(&self.left, &self.right, J::KEEP_LEFT)
+        } else {
+            self.right_table
+                .likely_pairs(&self.right, &self.left, J::KEEP_RIGHT)
+        };
+        self.joined.produced().saturating_add(pending)
+    }
+
+    fn pull_probe(&mut self) -> bool {
+        if self.table_left {
+            self.right.pull()
+        } else {
+            self.left.pull()
+        }
+    }
+}
+
+impl<'a, Del1, Del2, K, KF1, KF2, J> Deluge for JoinByKey<'a, Del1, Del2, K, KF1, KF2, J>
+where
+    Del1: Deluge + 'a,
+    Del2: Deluge + 'a,
+    Del1::Item: Clone,
+    Del2::Item: Clone,
+    K: Hash + Eq,
+    KF1: Fn(&Del1::Item) -> K,
+    KF2: Fn(&Del2::Item) -> K,
+    J: JoinKind<Del1::Item, Del2::Item>,
+{
+    type Item = J::Item;
+    type Output<'x>
+        = JoinFuture<'x, 'a, Del1, Del2, K, KF1, KF2, J>
+    where
+        Self: 'x;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        let index = self.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+
+            // Keys can repeat on both sides, so there can be more pairs than elements.
+            // Make sure there might be one more before handing out another future,
+            // the ones left over resolve to `None` once the join is complete.
+            while state.possible_pairs() <= state.joined.issued() {
+                if !state.right.pull() && !state.left.pull() {
+                    return None;
+                }
+            }
+
+            Some(state.joined.issue())
+        })?;
+
+        Some(JoinFuture { join: self, index })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.state.with(|_, state| {
+            let state = state.lock().unwrap();
+            // Every pair of elements can match, on top of each of them staying unmatched
+            let upper = state
+                .left
+                .size_hint()
+                .zip(state.right.size_hint())
+                .and_then(|(left, right)| {
+                    left.checked_mul(right)?
+                        .checked_add(left)?
+                        .checked_add(right)
+                })
+                .map(|upper| upper.saturating_sub(state.joined.issued()));
+
+            (0, upper)
+        })
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+
+            // `next` hands out a future for every pair the elements could possibly produce,
+            // which would be every combination of them until they are evaluated.
+            // Hold off while the futures handed out are waiting for their pairs instead,
+            // allowing one more for each probing element, which usually matches once.
+            while state.joined.issued() > state.joined.produced()
+                && !state.joined.is_finished()
+                && state.likely_pairs::<J>() <= state.joined.issued()
+            {
+                if !state.pull_probe() {
+                    if !state.ready_wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                        state.ready_wakers.push(cx.waker().clone());
+                    }
+                    return Poll::Pending;
+                }
+            }
+
+            Poll::Ready(())
+        })
+    }
+}
+
+pub struct JoinFuture<'x, 'a, Del1, Del2, K, KF1, KF2, J>
+where
+    Del1: Deluge + 'a,
+    Del2: Deluge + 'a,
+{
+    join: &'x JoinByKey<'a, Del1, Del2, K, KF1, KF2, J>,
+    index: usize,
+}
+
+impl<'x, 'a, Del1, Del2, K, KF1, KF2, J> Future for JoinFuture<'x, 'a, Del1, Del2, K, KF1, KF2, J>
+where
+    Del1: Deluge + 'a,
+    Del2: Deluge + 'a,
+    Del1::Item: Clone,
+    Del2::Item: Clone,
+    K: Hash + Eq,
+    KF1: Fn(&Del1::Item) -> K,
+    KF2: Fn(&Del2::Item) -> K,
+    J: JoinKind<Del1::Item, Del2::Item>,
+{
+    type Output = Option<J::Item>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (join, index) = (self.join, self.index);
+        let joined = join.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+            state.advance::<J, _, _>(index, &join.key_left, &join.key_right, cx);
+
+            // There are fewer pairs than futures when some don't match or get filtered out
+            state.joined.poll_take(index, cx)
+        });
+
+        match joined {
+            Poll::Ready(Some(Err(payload))) => std::panic::resume_unwind(payload),
+            Poll::Ready(Some(Ok((left, right)))) => Poll::Ready(Some(J::item(left, right))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<'x, 'a, Del1, Del2, K, KF1, KF2, J> Drop for JoinFuture<'x, 'a, Del1, Del2, K, KF1, KF2, J>
+where
+    Del1: Deluge + 'a,
+    Del2: Deluge + 'a,
+{
+    fn drop(&mut self) {
+        let index = self.index;
+        self.join.state.with(|_, state| {
+            if let Ok(mut state) = state.lock() {
+                state.joined.release(index);
+            }
+        });
+    }
+}
@@ -1,12 +1,17 @@
 use crate::deluge::Deluge;
+use std::sync::Mutex;
 
 pub struct Last<Del> {
     deluge: Del,
+    item_provided: Mutex<bool>,
 }
 
 impl<Del> Last<Del> {
     pub(crate) fn new(deluge: Del) -> Self {
-        Self { deluge }
+        Self {
+            deluge,
+            item_provided: Mutex::new(false),
+        }
     }
 }
 
@@ -15,9 +20,18 @@ where
     Del: Deluge,
 {
     type Item = Del::Item;
-    type Output<'a> = Del::Output<'a> where Self: 'a;
+    type Output<'a>
+        = Del::Output<'a>
+    where
+        Self: 'a;
 
     fn next(&self) -> Option<Self::Output<'_>> {
+        let mut item_provided = self.item_provided.lock().unwrap();
+        if *item_provided {
+            return None;
+        }
+        *item_provided = true;
+
         let mut previous_value = None;
         while let Some(v) = self.deluge.next() {
             previous_value = Some(v);
@@ -25,4 +39,18 @@ where
 
         previous_value
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if *self.item_provided.lock().unwrap() {
+            (0, Some(0))
+        } else {
+            // The last element might be a filtered out one, unless none of them are
+            let (lower, upper) = self.deluge.size_hint();
+            let exact = Some(lower) == upper;
+            (
+                (exact && lower > 0) as usize,
+                Some(upper.map_or(1, |upper| upper.min(1))),
+            )
+        }
+    }
 }
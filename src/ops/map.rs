@@ -1,5 +1,10 @@
 use crate::deluge::Deluge;
+use crate::helpers::element_fn::ElementFn;
+use futures::ready;
+use pin_project::pin_project;
 use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
 pub struct Map<Del, F> {
     deluge: Del,
@@ -15,21 +20,75 @@ impl<Del, F> Map<Del, F> {
 impl<InputDel, Fut, F> Deluge for Map<InputDel, F>
 where
     InputDel: Deluge,
-    F: Fn(InputDel::Item) -> Fut + Send,
-    Fut: Future + Send,
-    <Fut as Future>::Output: Send,
+    F: Fn(InputDel::Item) -> Fut,
+    Fut: Future,
 {
     type Item = Fut::Output;
-    type Output<'a> = impl Future<Output = Option<Self::Item>> + 'a where Self: 'a;
+    type Output<'a>
+        = MapFuture<'a, InputDel, F>
+    where
+        Self: 'a;
 
     fn next(&self) -> Option<Self::Output<'_>> {
-        self.deluge.next().map(|item| async {
-            let item = item.await;
-            if let Some(item) = item {
-                Some((self.f)(item).await)
-            } else {
-                None
-            }
+        self.deluge.next().map(|item| MapFuture {
+            f: &self.f,
+            state: MapState::Input { item },
         })
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deluge.size_hint()
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
+}
+
+#[pin_project]
+pub struct MapFuture<'a, Del: Deluge + 'a, F: ElementFn<Del::Item>> {
+    f: &'a F,
+    #[pin]
+    state: MapState<Del::Output<'a>, F::Future>,
+}
+
+/// Waits for the input element, then for the future `f` created from it
+#[pin_project(project = MapStateProj)]
+pub(crate) enum MapState<In, Fut> {
+    Input {
+        #[pin]
+        item: In,
+    },
+    Output {
+        #[pin]
+        future: Fut,
+    },
+}
+
+impl<'a, Del, F> Future for MapFuture<'a, Del, F>
+where
+    Del: Deluge + 'a,
+    F: ElementFn<Del::Item>,
+{
+    type Output = Option<<F::Future as Future>::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                MapStateProj::Input { item } => match ready!(item.poll(cx)) {
+                    Some(item) => {
+                        let future = this.f.call(item);
+                        this.state.set(MapState::Output { future });
+                    }
+                    None => return Poll::Ready(None),
+                },
+                MapStateProj::Output { future } => return future.poll(cx).map(Some),
+            }
+        }
+    }
 }
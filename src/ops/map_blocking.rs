@@ -0,0 +1,110 @@
+use crate::deluge::Deluge;
+use crate::ops::map::{MapState, MapStateProj};
+use crate::runtime::{DefaultRuntime, Runtime};
+use futures::future::BoxFuture;
+use futures::ready;
+use pin_project::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+pub struct MapBlocking<Del, F, R = DefaultRuntime> {
+    deluge: Del,
+    // Shared with the blocking tasks, which have to own everything they touch
+    f: Arc<F>,
+    runtime: R,
+}
+
+impl<Del, F> MapBlocking<Del, F> {
+    pub(crate) fn new(deluge: Del, f: F) -> Self {
+        Self {
+            deluge,
+            f: Arc::new(f),
+            runtime: DefaultRuntime::default(),
+        }
+    }
+}
+
+impl<Del, F, R> MapBlocking<Del, F, R> {
+    /// Runs the blocking closures on a different `runtime`
+    pub fn with_runtime<R2: Runtime>(self, runtime: R2) -> MapBlocking<Del, F, R2> {
+        MapBlocking {
+            deluge: self.deluge,
+            f: self.f,
+            runtime,
+        }
+    }
+}
+
+impl<InputDel, F, Out, R> Deluge for MapBlocking<InputDel, F, R>
+where
+    InputDel: Deluge,
+    InputDel::Item: Send + 'static,
+    F: Fn(InputDel::Item) -> Out + Send + Sync + 'static,
+    Out: Send + 'static,
+    R: Runtime,
+{
+    type Item = Out;
+    type Output<'a>
+        = MapBlockingFuture<'a, InputDel, F, Out, R>
+    where
+        Self: 'a;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.deluge.next().map(|item| MapBlockingFuture {
+            f: &self.f,
+            runtime: &self.runtime,
+            state: MapState::Input { item },
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deluge.size_hint()
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
+}
+
+#[pin_project]
+pub struct MapBlockingFuture<'a, Del: Deluge + 'a, F, Out, R> {
+    f: &'a Arc<F>,
+    runtime: &'a R,
+    #[pin]
+    state: MapState<Del::Output<'a>, BoxFuture<'static, Out>>,
+}
+
+impl<'a, Del, F, Out, R> Future for MapBlockingFuture<'a, Del, F, Out, R>
+where
+    Del: Deluge + 'a,
+    Del::Item: Send + 'static,
+    F: Fn(Del::Item) -> Out + Send + Sync + 'static,
+    Out: Send + 'static,
+    R: Runtime,
+{
+    type Output = Option<Out>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                MapStateProj::Input { item } => match ready!(item.poll(cx)) {
+                    Some(item) => {
+                        let f = Arc::clone(this.f);
+                        // Dropping this future does not stop a closure that has already started
+                        let future = this.runtime.spawn_blocking(move || f(item));
+                        this.state.set(MapState::Output { future });
+                    }
+                    None => return Poll::Ready(None),
+                },
+                MapStateProj::Output { future } => return future.poll(cx).map(Some),
+            }
+        }
+    }
+}
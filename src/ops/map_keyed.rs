@@ -0,0 +1,141 @@
+use crate::deluge::Deluge;
+use crate::helpers::element_fn::ElementFn;
+use crate::helpers::keyed_queue::{KeyedQueue, Ticket};
+use futures::ready;
+use pin_project::pin_project;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+pub struct MapKeyed<Del, K, KF, F> {
+    deluge: Del,
+    key_fn: KF,
+    f: F,
+
+    queue: KeyedQueue<K>,
+    provided_elems: AtomicUsize,
+}
+
+impl<Del, K, KF, F> MapKeyed<Del, K, KF, F>
+where
+    K: Hash + Eq + Clone,
+{
+    pub(crate) fn new(deluge: Del, key_fn: KF, f: F) -> Self {
+        Self {
+            deluge,
+            key_fn,
+            f,
+
+            queue: KeyedQueue::new(),
+            provided_elems: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<InputDel, K, KF, Fut, F> Deluge for MapKeyed<InputDel, K, KF, F>
+where
+    InputDel: Deluge,
+    K: Hash + Eq + Clone,
+    KF: Fn(&InputDel::Item) -> K,
+    F: Fn(InputDel::Item) -> Fut,
+    Fut: Future,
+{
+    type Item = Fut::Output;
+    type Output<'a>
+        = MapKeyedFuture<'a, InputDel, K, KF, F>
+    where
+        Self: 'a;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.deluge.next().map(|item| {
+            let idx = self.provided_elems.fetch_add(1, Ordering::Relaxed);
+
+            MapKeyedFuture {
+                map: self,
+                // The ticket is taken eagerly, so elements that are filtered out
+                // or dropped without being polled still release their place in line
+                ticket: Some(self.queue.ticket(idx)),
+                state: KeyedState::Input { item },
+            }
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deluge.size_hint()
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked() + self.queue.parked()
+    }
+}
+
+#[pin_project]
+pub struct MapKeyedFuture<'a, Del, K, KF, F>
+where
+    Del: Deluge + 'a,
+    K: Hash + Eq + Clone,
+    F: ElementFn<Del::Item>,
+{
+    map: &'a MapKeyed<Del, K, KF, F>,
+    // Released when the element completes or is dropped
+    ticket: Option<Ticket<'a, K>>,
+    #[pin]
+    state: KeyedState<Del::Output<'a>, Del::Item, F::Future>,
+}
+
+#[pin_project(project = KeyedStateProj)]
+enum KeyedState<In, Item, Fut> {
+    Input {
+        #[pin]
+        item: In,
+    },
+    Waiting {
+        item: Option<Item>,
+    },
+    Output {
+        #[pin]
+        future: Fut,
+    },
+}
+
+impl<'a, Del, K, KF, F> Future for MapKeyedFuture<'a, Del, K, KF, F>
+where
+    Del: Deluge + 'a,
+    K: Hash + Eq + Clone,
+    KF: Fn(&Del::Item) -> K,
+    F: ElementFn<Del::Item>,
+{
+    type Output = Option<<F::Future as Future>::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                KeyedStateProj::Input { item } => match ready!(item.poll(cx)) {
+                    Some(item) => {
+                        let key = (this.map.key_fn)(&item);
+                        this.ticket.as_mut().unwrap().enqueue(key);
+                        this.state.set(KeyedState::Waiting { item: Some(item) });
+                    }
+                    None => return Poll::Ready(None),
+                },
+                KeyedStateProj::Waiting { item } => {
+                    ready!(this.ticket.as_ref().unwrap().poll_turn(cx));
+                    let future = this.map.f.call(item.take().unwrap());
+                    this.state.set(KeyedState::Output { future });
+                }
+                KeyedStateProj::Output { future } => {
+                    let output = ready!(future.poll(cx));
+                    this.ticket.take();
+                    return Poll::Ready(Some(output));
+                }
+            }
+        }
+    }
+}
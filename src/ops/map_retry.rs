@@ -0,0 +1,289 @@
+use crate::deluge::Deluge;
+use crate::helpers::element_fn::ElementFn;
+use crate::runtime::{DefaultRuntime, Runtime};
+use futures::ready;
+use pin_project::pin_project;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Describes how many times a failed element is retried
+/// and how long to wait between subsequent attempts.
+///
+/// By default an element is attempted at most three times.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    backoff: Backoff,
+    max_attempts: usize,
+    max_delay: Option<Duration>,
+    jitter: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Backoff {
+    Fixed(Duration),
+    Exponential { initial: Duration, factor: u32 },
+}
+
+impl RetryPolicy {
+    /// Waits for the same `delay` after each failed attempt
+    pub fn fixed(delay: Duration) -> Self {
+        Self::with_backoff(Backoff::Fixed(delay))
+    }
+
+    /// Waits for `initial` after the first failed attempt,
+    /// multiplying the delay by `factor` after each subsequent one
+    pub fn exponential(initial: Duration, factor: u32) -> Self {
+        Self::with_backoff(Backoff::Exponential { initial, factor })
+    }
+
+    fn with_backoff(backoff: Backoff) -> Self {
+        Self {
+            backoff,
+            max_attempts: 3,
+            max_delay: None,
+            jitter: false,
+        }
+    }
+
+    /// Sets the total number of attempts, including the first one
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = std::cmp::max(max_attempts, 1);
+        self
+    }
+
+    /// Caps the delay between subsequent attempts
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// Picks a random delay between zero and the one computed by the backoff,
+    /// so elements failing at the same time don't retry at the same time
+    pub fn jitter(mut self) -> Self {
+        self.jitter = true;
+        self
+    }
+
+    /// How long to wait after `failed_attempts` attempts have failed
+    fn delay(&self, failed_attempts: usize) -> Duration {
+        let delay = match self.backoff {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, factor } => {
+                let exponent = u32::try_from(failed_attempts - 1).unwrap_or(u32::MAX);
+                initial
+                    .checked_mul(factor.saturating_pow(exponent))
+                    .unwrap_or(Duration::MAX)
+            }
+        };
+
+        let delay = match self.max_delay {
+            Some(max_delay) => std::cmp::min(delay, max_delay),
+            None => delay,
+        };
+
+        if self.jitter {
+            // Every `RandomState` is seeded differently, which is good enough for jitter.
+            // Scaling the nanoseconds by a 32 bit fraction can't overflow or round past
+            // the delay, unlike `Duration::mul_f64` with an uncapped backoff.
+            let random = RandomState::new().build_hasher().finish() >> 32;
+            let nanos = (delay.as_nanos() * u128::from(random)) >> 32;
+            Duration::new(
+                (nanos / 1_000_000_000) as u64,
+                (nanos % 1_000_000_000) as u32,
+            )
+        } else {
+            delay
+        }
+    }
+}
+
+pub struct MapRetry<Del, F, R = DefaultRuntime> {
+    deluge: Del,
+    policy: RetryPolicy,
+    f: F,
+    runtime: R,
+}
+
+impl<Del, F> MapRetry<Del, F> {
+    pub(crate) fn new(deluge: Del, policy: RetryPolicy, f: F) -> Self {
+        Self {
+            deluge,
+            policy,
+            f,
+            runtime: DefaultRuntime::default(),
+        }
+    }
+}
+
+impl<Del, F, R> MapRetry<Del, F, R> {
+    /// Waits between the attempts with a different `runtime`
+    pub fn with_runtime<R2: Runtime>(self, runtime: R2) -> MapRetry<Del, F, R2> {
+        MapRetry {
+            deluge: self.deluge,
+            policy: self.policy,
+            f: self.f,
+            runtime,
+        }
+    }
+}
+
+impl<InputDel, Fut, F, T, E, R> Deluge for MapRetry<InputDel, F, R>
+where
+    InputDel: Deluge,
+    R: Runtime,
+    InputDel::Item: Clone,
+    F: Fn(InputDel::Item) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    type Item = Result<T, E>;
+    type Output<'a>
+        = MapRetryFuture<'a, InputDel, F, R>
+    where
+        Self: 'a;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.deluge.next().map(|item| MapRetryFuture {
+            map: self,
+            item: None,
+            failed_attempts: 0,
+            state: RetryState::Input { item },
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deluge.size_hint()
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
+}
+
+#[pin_project]
+pub struct MapRetryFuture<'a, Del, F, R>
+where
+    Del: Deluge + 'a,
+    F: ElementFn<Del::Item>,
+    R: Runtime,
+{
+    map: &'a MapRetry<Del, F, R>,
+    item: Option<Del::Item>,
+    failed_attempts: usize,
+    #[pin]
+    state: RetryState<Del::Output<'a>, F::Future, R::Sleep>,
+}
+
+#[pin_project(project = RetryStateProj)]
+enum RetryState<In, Fut, Sleep> {
+    Input {
+        #[pin]
+        item: In,
+    },
+    Attempt {
+        #[pin]
+        future: Fut,
+    },
+    Backoff {
+        #[pin]
+        sleep: Sleep,
+    },
+}
+
+impl<'a, Del, F, T, E, R> Future for MapRetryFuture<'a, Del, F, R>
+where
+    Del: Deluge + 'a,
+    Del::Item: Clone,
+    F: ElementFn<Del::Item>,
+    F::Future: Future<Output = Result<T, E>>,
+    R: Runtime,
+{
+    type Output = Option<Result<T, E>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                RetryStateProj::Input { item } => match ready!(item.poll(cx)) {
+                    Some(item) => {
+                        // The item is only kept around if it might be needed for a retry
+                        if this.map.policy.max_attempts > 1 {
+                            *this.item = Some(item.clone());
+                        }
+                        let future = this.map.f.call(item);
+                        this.state.set(RetryState::Attempt { future });
+                    }
+                    None => return Poll::Ready(None),
+                },
+                RetryStateProj::Attempt { future } => match ready!(future.poll(cx)) {
+                    Ok(v) => return Poll::Ready(Some(Ok(v))),
+                    Err(e) => {
+                        *this.failed_attempts += 1;
+                        if *this.failed_attempts >= this.map.policy.max_attempts {
+                            return Poll::Ready(Some(Err(e)));
+                        }
+
+                        // Retries happen within the element future,
+                        // so they keep occupying the same concurrency slot of the collector
+                        let delay = this.map.policy.delay(*this.failed_attempts);
+                        let sleep = this.map.runtime.sleep(delay);
+                        this.state.set(RetryState::Backoff { sleep });
+                    }
+                },
+                RetryStateProj::Backoff { sleep } => {
+                    ready!(sleep.poll(cx));
+                    let item = if *this.failed_attempts + 1 < this.map.policy.max_attempts {
+                        this.item.clone()
+                    } else {
+                        this.item.take()
+                    };
+                    let future = this.map.f.call(item.unwrap());
+                    this.state.set(RetryState::Attempt { future });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff_grows_until_capped() {
+        let policy = RetryPolicy::exponential(Duration::from_millis(10), 2)
+            .max_delay(Duration::from_millis(50));
+
+        assert_eq!(policy.delay(1), Duration::from_millis(10));
+        assert_eq!(policy.delay(2), Duration::from_millis(20));
+        assert_eq!(policy.delay(3), Duration::from_millis(40));
+        assert_eq!(policy.delay(4), Duration::from_millis(50));
+        assert_eq!(policy.delay(100), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn jitter_stays_within_the_delay() {
+        let policy = RetryPolicy::fixed(Duration::from_millis(10)).jitter();
+
+        for attempt in 1..100 {
+            assert!(policy.delay(attempt) <= Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn jitter_handles_uncapped_delays() {
+        let policy = RetryPolicy::exponential(Duration::from_secs(1), 10).jitter();
+
+        for attempt in 1..100 {
+            policy.delay(attempt);
+        }
+        assert!(RetryPolicy::fixed(Duration::MAX).jitter().delay(1) <= Duration::MAX);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::concurrency::{Acquire, ConcurrencyLimiter, Permit};
+use crate::deluge::Deluge;
+use crate::helpers::element_fn::ElementFn;
+use futures::ready;
+use pin_project::pin_project;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+pub struct MapWeighted<Del, CF, F> {
+    deluge: Del,
+    limiter: ConcurrencyLimiter,
+    cost_fn: CF,
+    f: F,
+}
+
+impl<Del, CF, F> MapWeighted<Del, CF, F> {
+    pub(crate) fn new(
+        deluge: Del,
+        limiter: impl Into<ConcurrencyLimiter>,
+        cost_fn: CF,
+        f: F,
+    ) -> Self {
+        Self {
+            deluge,
+            limiter: limiter.into(),
+            cost_fn,
+            f,
+        }
+    }
+}
+
+impl<InputDel, Fut, CF, F> Deluge for MapWeighted<InputDel, CF, F>
+where
+    InputDel: Deluge,
+    CF: Fn(&InputDel::Item) -> usize,
+    F: Fn(InputDel::Item) -> Fut,
+    Fut: Future,
+{
+    type Item = Fut::Output;
+    type Output<'a>
+        = MapWeightedFuture<'a, InputDel, CF, F>
+    where
+        Self: 'a;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.deluge.next().map(|item| MapWeightedFuture {
+            map: self,
+            permit: None,
+            state: WeightedState::Input { item },
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deluge.size_hint()
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
+}
+
+#[pin_project]
+pub struct MapWeightedFuture<'a, Del: Deluge + 'a, CF, F: ElementFn<Del::Item>> {
+    map: &'a MapWeighted<Del, CF, F>,
+    // Held until `f` completes
+    permit: Option<Permit>,
+    #[pin]
+    state: WeightedState<Del::Output<'a>, Del::Item, F::Future>,
+}
+
+#[pin_project(project = WeightedStateProj)]
+enum WeightedState<In, Item, Fut> {
+    Input {
+        #[pin]
+        item: In,
+    },
+    Acquire {
+        acquire: Acquire,
+        item: Option<Item>,
+    },
+    Output {
+        #[pin]
+        future: Fut,
+    },
+}
+
+impl<'a, Del, CF, F> Future for MapWeightedFuture<'a, Del, CF, F>
+where
+    Del: Deluge + 'a,
+    CF: Fn(&Del::Item) -> usize,
+    F: ElementFn<Del::Item>,
+{
+    type Output = Option<<F::Future as Future>::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        loop {
+            match this.state.as_mut().project() {
+                WeightedStateProj::Input { item } => match ready!(item.poll(cx)) {
+                    Some(item) => {
+                        // The limiter admits an element costing more than its whole budget
+                        // once nothing else is running, so a huge element can't deadlock the pipeline
+                        let acquire = this.map.limiter.acquire((this.map.cost_fn)(&item));
+                        this.state.set(WeightedState::Acquire {
+                            acquire,
+                            item: Some(item),
+                        });
+                    }
+                    None => return Poll::Ready(None),
+                },
+                WeightedStateProj::Acquire { acquire, item } => {
+                    *this.permit = Some(ready!(Pin::new(acquire).poll(cx)));
+                    let future = this.map.f.call(item.take().unwrap());
+                    this.state.set(WeightedState::Output { future });
+                }
+                WeightedStateProj::Output { future } => {
+                    let output = ready!(future.poll(cx));
+                    this.permit.take();
+                    return Poll::Ready(Some(output));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use crate::deluge::Deluge;
+use crate::helpers::output_slots::OutputSlots;
+use crate::helpers::owned_deluge::{Borrowers, OwnedDeluge};
+use crate::ops::catch_unwind::PanicPayload;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+const DEFAULT_LOOKAHEAD: usize = 4;
+
+pub struct MergeSortedBy<'a, Del, F>
+where
+    Del: Deluge + 'a,
+{
+    cmp: F,
+    lookahead: usize,
+    state: OwnedDeluge<'a, Vec<Del>, State>,
+}
+
+// The buffered futures borrow the sources
+struct State;
+
+impl<'x, Del: Deluge> Borrowers<'x, Vec<Del>> for State {
+    type Of = Mutex<MergeState<'x, Del>>;
+}
+
+struct MergeState<'a, Del: Deluge + 'a> {
+    sources: Vec<Source<'a, Del>>,
+    // A panicked element takes up an output of its own, so it's resumed by the future taking it
+    merged: OutputSlots<Result<Del::Item, PanicPayload>>,
+    // Collectors waiting for the merge to get further before asking for another future
+    ready_wakers: Vec<Waker>,
+}
+
+struct Source<'a, Del: Deluge + 'a> {
+    deluge: &'a Del,
+    buffer: VecDeque<Slot<Del::Output<'a>, Del::Item>>,
+    pulled: usize,
+    exhausted: bool,
+}
+
+enum Slot<Fut, Item> {
+    Pending(Pin<Box<Fut>>),
+    Ready(Option<Item>),
+    Panicked(PanicPayload),
+}
+
+impl<'a, Del, F> MergeSortedBy<'a, Del, F>
+where
+    Del: Deluge + 'a,
+{
+    pub(crate) fn new(deluges: Vec<Del>, cmp: F) -> Self {
+        Self {
+            cmp,
+            lookahead: DEFAULT_LOOKAHEAD,
+            state: OwnedDeluge::new(deluges, |deluges| {
+                Mutex::new(MergeState {
+                    sources: deluges.iter().map(Source::new).collect(),
+                    merged: OutputSlots::new(),
+                    ready_wakers: Vec::new(),
+                })
+            }),
+        }
+    }
+
+    /// Evaluates at most `lookahead` elements of each source ahead of the merged output.
+    /// The default is 4 and a lookahead of 0 is treated as 1.
+    pub fn with_lookahead(mut self, lookahead: usize) -> Self {
+        self.lookahead = lookahead.max(1);
+        self
+    }
+}
+
+impl<'a, Del: Deluge + 'a> Source<'a, Del> {
+    fn new(deluge: &'a Del) -> Self {
+        Self {
+            deluge,
+            buffer: VecDeque::new(),
+            pulled: 0,
+            exhausted: false,
+        }
+    }
+
+    /// How many more elements the source is known to have, without pulling them
+    fn remaining(&self) -> (usize, Option<usize>) {
+        if self.exhausted {
+            (0, Some(0))
+        } else {
+            self.deluge.size_hint()
+        }
+    }
+
+    fn pull(&mut self) -> bool {
+        if self.exhausted {
+            return false;
+        }
+
+        match self.deluge.next() {
+            Some(future) => {
+                self.buffer.push_back(Slot::Pending(Box::pin(future)));
+                self.pulled += 1;
+                true
+            }
+            None => {
+                self.exhausted = true;
+                false
+            }
+        }
+    }
+
+    /// Evaluates the buffered elements until the first one is known,
+    /// resolving to `false` if the source ran out
+    fn poll_head(&mut self, lookahead: usize, cx: &mut Context<'_>) -> Poll<bool> {
+        loop {
+            while self.buffer.len() < lookahead && self.pull() {}
+
+            for slot in self.buffer.iter_mut().take(lookahead) {
+                if let Slot::Pending(future) = slot {
+                    // A panic unwinding past the lock would poison it for the other futures
+                    match std::panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                        Ok(Poll::Ready(item)) => *slot = Slot::Ready(item),
+                        Ok(Poll::Pending) => (),
+                        Err(payload) => *slot = Slot::Panicked(payload),
+                    }
+                }
+            }
+
+            match self.buffer.front() {
+                // Filtered out elements don't take part in the merge
+                Some(Slot::Ready(None)) => {
+                    self.buffer.pop_front();
+                }
+                Some(Slot::Ready(Some(_)) | Slot::Panicked(_)) => return Poll::Ready(true),
+                Some(Slot::Pending(_)) => return Poll::Pending,
+                None => return Poll::Ready(false),
+            }
+        }
+    }
+
+    fn head(&self) -> Option<&Del::Item> {
+        match self.buffer.front() {
+            Some(Slot::Ready(Some(item))) => Some(item),
+            _ => None,
+        }
+    }
+
+    fn pop_head(&mut self) -> Option<Del::Item> {
+        match self.buffer.pop_front() {
+            Some(Slot::Ready(item)) => item,
+            _ => None,
+        }
+    }
+}
+
+impl<'a, Del: Deluge + 'a> MergeState<'a, Del> {
+    /// Pulls a future out of the source with the fewest buffered ones
+    /// among those that don't know how many elements they have left,
+    /// as long as it has fewer than `lookahead` of them
+    fn pull_any(&mut self, lookahead: usize) -> bool {
+        loop {
+            let source = self
+                .sources
+                .iter_mut()
+                .filter(|source| {
+                    let (lower, upper) = source.remaining();
+                    upper != Some(lower) && source.buffer.len() < lookahead
+                })
+                .min_by_key(|source| source.buffer.len());
+
+            match source {
+                Some(source) => {
+                    if source.pull() {
+                        return true;
+                    }
+                }
+                None => return false,
+            }
+        }
+    }
+
+    /// How many elements the sources have in total, counting the ones pulled already
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.sources
+            .iter()
+            .fold((0, Some(0)), |(lower, upper), source| {
+                let (source_lower, source_upper) = source.remaining();
+                (
+                    lower
+                        .saturating_add(source.pulled)
+                        .saturating_add(source_lower),
+                    upper.zip(source_upper).and_then(|(upper, source_upper)| {
+                        upper.checked_add(source.pulled)?.checked_add(source_upper)
+                    }),
+                )
+            })
+    }
+
+    /// Merges elements until the one at `index` is known
+    fn advance<F>(&mut self, index: usize, lookahead: usize, cmp: &F, cx: &mut Context<'_>)
+    where
+        F: Fn(&Del::Item, &Del::Item) -> Ordering,
+    {
+        let progress = |state: &Self| {
+            let pulled = state
+                .sources
+                .iter()
+                .map(|source| source.pulled)
+                .sum::<usize>();
+            (state.merged.produced(), pulled)
+        };
+        let progress_before = progress(self);
+        self.merge_until(index, lookahead, cmp, cx);
+
+        if progress(self) != progress_before || self.merged.is_finished() {
+            self.ready_wakers.drain(..).for_each(Waker::wake);
+        }
+    }
+
+    fn merge_until<F>(&mut self, index: usize, lookahead: usize, cmp: &F, cx: &mut Context<'_>)
+    where
+        F: Fn(&Del::Item, &Del::Item) -> Ordering,
+    {
+        while self.merged.produced() <= index && !self.merged.is_finished() {
+            // Every source needs its head evaluated before the smallest one can be picked
+            let mut pending = false;
+            for source in self.sources.iter_mut() {
+                pending |= source.poll_head(lookahead, cx).is_pending();
+            }
+            if pending {
+                return;
+            }
+
+            // Panicked elements can't be compared, so they go out as soon as they're at the head
+            let panicked = self
+                .sources
+                .iter_mut()
+                .find(|source| matches!(source.buffer.front(), Some(Slot::Panicked(_))));
+            if let Some(Some(Slot::Panicked(payload))) =
+                panicked.map(|source| source.buffer.pop_front())
+            {
+                self.merged.push(Err(payload));
+                continue;
+            }
+
+            // `min_by` returns the first of equal elements, which keeps the merge stable
+            let smallest = self
+                .sources
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, source)| source.head().map(|head| (idx, head)))
+                .min_by(|(_, a), (_, b)| cmp(a, b))
+                .map(|(idx, _)| idx);
+
+            match smallest {
+                Some(idx) => {
+                    let item = self.sources[idx].pop_head().unwrap();
+                    self.merged.push(Ok(item));
+                }
+                None => self.merged.finish(),
+            }
+        }
+    }
+}
+
+impl<'a, Del, F> Deluge for MergeSortedBy<'a, Del, F>
+where
+    Del: Deluge + 'a,
+    F: Fn(&Del::Item, &Del::Item) -> Ordering,
+{
+    type Item = Del::Item;
+    type Output<'x>
+        = MergeFuture<'x, 'a, Del, F>
+    where
+        Self: 'x;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        let index = self.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+
+            // There are as many merged elements as there are elements in all the sources,
+            // so make sure there might be one more before handing out another future.
+            // The sources are only pulled within the lookahead, after that the futures
+            // are handed out up to the upper bound and the extra ones resolve to `None`.
+            // Pulling further is the only way to tell if there is no upper bound though.
+            loop {
+                let (lower, upper) = state.size_hint();
+                let issued = state.merged.issued();
+                if lower > issued {
+                    break;
+                }
+                if upper.is_some_and(|upper| upper <= issued) {
+                    return None;
+                }
+                if state.pull_any(self.lookahead) {
+                    continue;
+                }
+                if upper.is_some() {
+                    break;
+                }
+                if !state.pull_any(usize::MAX) {
+                    return None;
+                }
+            }
+
+            Some(state.merged.issue())
+        })?;
+
+        Some(MergeFuture { merge: self, index })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.state.with(|_, state| {
+            let state = state.lock().unwrap();
+            let upper = state.size_hint().1;
+            (0, upper.map(|upper| upper - state.merged.issued()))
+        })
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+
+            // Once the buffers are full, hold off while the futures handed out
+            // are waiting for the merge to get further, instead of going past the lookahead
+            while state.merged.issued() > state.merged.produced() && !state.merged.is_finished() {
+                let (lower, upper) = state.size_hint();
+                let issued = state.merged.issued();
+                if lower > issued || upper.is_some_and(|upper| upper <= issued) {
+                    break;
+                }
+                if !state.pull_any(self.lookahead) {
+                    if !state.ready_wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                        state.ready_wakers.push(cx.waker().clone());
+                    }
+                    return Poll::Pending;
+                }
+            }
+
+            Poll::Ready(())
+        })
+    }
+}
+
+pub struct MergeFuture<'x, 'a, Del, F>
+where
+    Del: Deluge + 'a,
+{
+    merge: &'x MergeSortedBy<'a, Del, F>,
+    index: usize,
+}
+
+impl<'x, 'a, Del, F> Future for MergeFuture<'x, 'a, Del, F>
+where
+    Del: Deluge + 'a,
+    F: Fn(&Del::Item, &Del::Item) -> Ordering,
+{
+    type Output = Option<Del::Item>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (merge, index) = (self.merge, self.index);
+        let merged = merge.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+            state.advance(index, merge.lookahead, &merge.cmp, cx);
+            // Once the merge finishes early, some of the elements were filtered out
+            state.merged.poll_take(index, cx)
+        });
+
+        match merged {
+            Poll::Ready(Some(Err(payload))) => std::panic::resume_unwind(payload),
+            Poll::Ready(Some(Ok(item))) => Poll::Ready(Some(item)),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<'x, 'a, Del, F> Drop for MergeFuture<'x, 'a, Del, F>
+where
+    Del: Deluge + 'a,
+{
+    fn drop(&mut self) {
+        let index = self.index;
+        self.merge.state.with(|_, state| {
+            if let Ok(mut state) = state.lock() {
+                state.merged.release(index);
+            }
+        });
+    }
+}
@@ -1,45 +1,81 @@
 pub mod all;
-#[cfg(feature = "async-runtime")]
 pub mod all_par;
 pub mod any;
-#[cfg(feature = "async-runtime")]
 pub mod any_par;
+pub mod boxed;
+pub mod catch_unwind;
 pub mod chain;
 pub mod collect;
-#[cfg(feature = "async-runtime")]
 pub mod collect_par;
+#[cfg(feature = "async-runtime")]
+pub mod collect_until;
 pub mod count;
+pub mod crawl;
+pub mod either;
 //pub mod filter;
 pub mod filter_map;
 pub mod first;
 pub mod fold;
-#[cfg(feature = "async-runtime")]
 pub mod fold_par;
+pub mod fuse;
+pub mod interleave;
+pub mod join_by_key;
 pub mod last;
 pub mod map;
+#[cfg(feature = "async-runtime")]
+pub mod map_blocking;
+pub mod map_keyed;
+#[cfg(feature = "async-runtime")]
+pub mod map_retry;
+pub mod map_weighted;
+pub mod merge_sorted_by;
+#[cfg(feature = "async-runtime")]
+pub mod rate_limit;
+pub mod split;
+pub mod split_by_key;
 pub mod take;
+pub mod tee;
 #[cfg(feature = "async-runtime")]
+pub mod timeout;
 pub mod zip;
 
 pub(crate) use all::*;
-#[cfg(feature = "async-runtime")]
 pub(crate) use all_par::*;
 pub(crate) use any::*;
-#[cfg(feature = "async-runtime")]
 pub(crate) use any_par::*;
+pub(crate) use boxed::*;
+pub(crate) use catch_unwind::*;
 pub(crate) use chain::*;
 pub(crate) use collect::*;
-#[cfg(feature = "async-runtime")]
 pub(crate) use collect_par::*;
+#[cfg(feature = "async-runtime")]
+pub(crate) use collect_until::*;
 pub(crate) use count::*;
+pub(crate) use crawl::*;
+pub(crate) use either::*;
 //pub(crate) use filter::*;
 pub(crate) use filter_map::*;
 pub(crate) use first::*;
 pub(crate) use fold::*;
-#[cfg(feature = "async-runtime")]
 pub(crate) use fold_par::*;
+pub(crate) use fuse::*;
+pub(crate) use interleave::*;
+pub(crate) use join_by_key::*;
 pub(crate) use last::*;
 pub(crate) use map::*;
+#[cfg(feature = "async-runtime")]
+pub(crate) use map_blocking::*;
+pub(crate) use map_keyed::*;
+#[cfg(feature = "async-runtime")]
+pub(crate) use map_retry::*;
+pub(crate) use map_weighted::*;
+pub(crate) use merge_sorted_by::*;
+#[cfg(feature = "async-runtime")]
+pub(crate) use rate_limit::*;
+pub(crate) use split::*;
+pub(crate) use split_by_key::*;
 pub(crate) use take::*;
+pub(crate) use tee::*;
 #[cfg(feature = "async-runtime")]
+pub(crate) use timeout::*;
 pub(crate) use zip::*;
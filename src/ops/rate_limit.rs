@@ -0,0 +1,198 @@
+use crate::deluge::Deluge;
+use crate::helpers::token_bucket::TokenBucket;
+use crate::runtime::{DefaultRuntime, Runtime};
+use futures::ready;
+use pin_project::{pin_project, pinned_drop};
+use std::collections::{BTreeMap, BTreeSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+pub struct RateLimit<Del, R = DefaultRuntime> {
+    deluge: Del,
+    tokens: Mutex<Tokens>,
+    pulled: AtomicUsize,
+    runtime: R,
+}
+
+struct Tokens {
+    bucket: TokenBucket,
+    // Index of the first element that has no token yet
+    unreserved: usize,
+    // Tokens taken for the elements that weren't polled yet when a later one was
+    reserved: BTreeMap<usize, Instant>,
+    // Elements dropped before anyone took a token for them, which don't need one anymore
+    dropped: BTreeSet<usize>,
+}
+
+impl Tokens {
+    /// Takes the token of the element at `index`.
+    ///
+    /// Tokens are handed out in the order of the elements, so the ordered output
+    /// isn't held back by late elements that happened to be polled first.
+    fn take(&mut self, index: usize) -> Instant {
+        if let Some(allowed_at) = self.reserved.remove(&index) {
+            return allowed_at;
+        }
+
+        while self.unreserved < index {
+            if !self.dropped.remove(&self.unreserved) {
+                self.reserved.insert(self.unreserved, self.bucket.reserve());
+            }
+            self.unreserved += 1;
+        }
+        self.unreserved = index + 1;
+        self.bucket.reserve()
+    }
+
+    /// Gives the token of a dropped element back to the bucket,
+    /// or makes sure it doesn't get one if it wasn't reserved yet
+    fn forget(&mut self, index: usize) {
+        if self.reserved.remove(&index).is_some() {
+            self.bucket.release();
+        } else if index >= self.unreserved {
+            self.dropped.insert(index);
+        }
+    }
+}
+
+impl<Del> RateLimit<Del> {
+    pub(crate) fn new(
+        deluge: Del,
+        permits: usize,
+        interval: Duration,
+        burst: impl Into<Option<usize>>,
+    ) -> Self {
+        Self {
+            deluge,
+            tokens: Mutex::new(Tokens {
+                bucket: TokenBucket::new(permits, interval, burst.into().unwrap_or(permits)),
+                unreserved: 0,
+                reserved: BTreeMap::new(),
+                dropped: BTreeSet::new(),
+            }),
+            pulled: AtomicUsize::new(0),
+            runtime: DefaultRuntime::default(),
+        }
+    }
+}
+
+impl<Del, R> RateLimit<Del, R> {
+    /// Waits for the permits with a different `runtime`
+    pub fn with_runtime<R2: Runtime>(self, runtime: R2) -> RateLimit<Del, R2> {
+        RateLimit {
+            deluge: self.deluge,
+            tokens: self.tokens,
+            pulled: self.pulled,
+            runtime,
+        }
+    }
+}
+
+impl<Del, R> Deluge for RateLimit<Del, R>
+where
+    Del: Deluge,
+    R: Runtime,
+{
+    type Item = Del::Item;
+    type Output<'a>
+        = RateLimitFuture<'a, Del, R>
+    where
+        Self: 'a;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.deluge.next().map(|item| RateLimitFuture {
+            runtime: &self.runtime,
+            // All the elements share the same bucket, no matter which worker evaluates them
+            tokens: &self.tokens,
+            token: Token::NotTaken,
+            index: self.pulled.fetch_add(1, Ordering::Relaxed),
+            delay: None,
+            item,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.deluge.size_hint()
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
+}
+
+#[pin_project(PinnedDrop)]
+pub struct RateLimitFuture<'a, Del: Deluge + 'a, R: Runtime> {
+    runtime: &'a R,
+    tokens: &'a Mutex<Tokens>,
+    // The token is taken on the first poll, so the elements waiting for the collector
+    // to pick them up don't use up their tokens and then all start at once
+    token: Token,
+    index: usize,
+    #[pin]
+    delay: Option<R::Sleep>,
+    #[pin]
+    item: Del::Output<'a>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Token {
+    NotTaken,
+    // Waiting for the time the token can be used at
+    Taken,
+    Used,
+}
+
+impl<'a, Del, R> Future for RateLimitFuture<'a, Del, R>
+where
+    Del: Deluge + 'a,
+    R: Runtime,
+{
+    type Output = Option<Del::Item>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        if *this.token == Token::NotTaken {
+            let allowed_at = this.tokens.lock().unwrap().take(*this.index);
+            let wait = allowed_at.saturating_duration_since(Instant::now());
+            if !wait.is_zero() {
+                this.delay.set(Some(this.runtime.sleep(wait)));
+            }
+            *this.token = Token::Taken;
+        }
+
+        if let Some(delay) = this.delay.as_mut().as_pin_mut() {
+            ready!(delay.poll(cx));
+            this.delay.set(None);
+        }
+        *this.token = Token::Used;
+
+        this.item.poll(cx)
+    }
+}
+
+#[pinned_drop]
+impl<'a, Del, R> PinnedDrop for RateLimitFuture<'a, Del, R>
+where
+    Del: Deluge + 'a,
+    R: Runtime,
+{
+    fn drop(self: Pin<&mut Self>) {
+        // An element dropped before it got to start doesn't use up a token,
+        // whether it took one itself or a later element took it for it
+        if let Ok(mut tokens) = self.tokens.lock() {
+            match self.token {
+                Token::NotTaken => tokens.forget(self.index),
+                Token::Taken => tokens.bucket.release(),
+                Token::Used => (),
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use crate::deluge::Deluge;
+use crate::helpers::owned_deluge::{Borrowers, OwnedDeluge};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// Decides which of the shards returned by `split` gets an element
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitMode {
+    /// The shards take turns, skipping the ones that were dropped
+    RoundRobin,
+    /// Each element goes to the first shard asking for one
+    NextFree,
+}
+
+/// One of the shards returned by `split`
+pub struct Split<'a, Del: Deluge + 'a> {
+    shard: usize,
+    shared: Arc<Shared<'a, Del>>,
+}
+
+struct Shared<'a, Del: Deluge + 'a> {
+    mode: SplitMode,
+    state: OwnedDeluge<'a, Del, State>,
+}
+
+// The elements pulled out of the deluge borrow it
+struct State;
+
+impl<'x, Del: Deluge> Borrowers<'x, Del> for State {
+    type Of = Mutex<SplitState<'x, Del>>;
+}
+
+type Element<'a, Del> = Pin<Box<<Del as Deluge>::Output<'a>>>;
+
+struct SplitState<'a, Del: Deluge + 'a> {
+    deluge: &'a Del,
+    // Elements pulled out for the other shards while looking for one's own.
+    // They aren't capped, as a shard that isn't being collected keeps its elements
+    // waiting here until it is, or until it's dropped and they become orphans
+    queues: Vec<VecDeque<Element<'a, Del>>>,
+    // Elements of the dropped shards, taken by whichever shard asks first
+    orphans: VecDeque<Element<'a, Del>>,
+    // The elements handed out to the shards, evaluated in place by their futures
+    handed_out: HashMap<usize, Element<'a, Del>>,
+    next_id: usize,
+    exhausted: bool,
+    live: Vec<bool>,
+    // Shards that returned `None` already, which keep doing so even if orphans show up later
+    finished: Vec<bool>,
+    turn: usize,
+}
+
+impl<'a, Del: Deluge + 'a> Split<'a, Del> {
+    pub(crate) fn new(deluge: Del, shards: usize, mode: SplitMode) -> Vec<Self> {
+        let shared = Arc::new(Shared {
+            mode,
+            state: OwnedDeluge::new(deluge, |deluge| {
+                Mutex::new(SplitState {
+                    deluge,
+                    queues: (0..shards).map(|_| VecDeque::new()).collect(),
+                    orphans: VecDeque::new(),
+                    handed_out: HashMap::new(),
+                    next_id: 0,
+                    exhausted: false,
+                    live: vec![true; shards],
+                    finished: vec![false; shards],
+                    turn: 0,
+                })
+            }),
+        });
+
+        (0..shards)
+            .map(|shard| Split {
+                shard,
+                shared: shared.clone(),
+            })
+            .collect()
+    }
+}
+
+impl<'a, Del: Deluge + 'a> SplitState<'a, Del> {
+    fn next_turn(&mut self) {
+        let shards = self.live.len();
+        for offset in 1..=shards {
+            let shard = (self.turn + offset) % shards;
+            if self.live[shard] {
+                self.turn = shard;
+                return;
+            }
+        }
+    }
+
+    /// Pulls the next element out of the deluge, which isn't asked again once it ran out
+    fn pull(&mut self) -> Option<Element<'a, Del>> {
+        if self.exhausted {
+            return None;
+        }
+
+        let future = self.deluge.next().map(Box::pin);
+        self.exhausted = future.is_none();
+        future
+    }
+
+    fn next_round_robin(&mut self, shard: usize) -> Option<Element<'a, Del>> {
+        if let Some(future) = self.queues[shard].pop_front() {
+            return Some(future);
+        }
+        if let Some(future) = self.orphans.pop_front() {
+            return Some(future);
+        }
+
+        loop {
+            let future = self.pull()?;
+            let turn = self.turn;
+            self.next_turn();
+            if turn == shard {
+                return Some(future);
+            }
+            self.queues[turn].push_back(future);
+        }
+    }
+}
+
+impl<'a, Del: Deluge + 'a> Deluge for Split<'a, Del> {
+    type Item = Del::Item;
+    type Output<'x>
+        = SplitFuture<'x, 'a, Del>
+    where
+        Self: 'x;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        let id = self.shared.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+            if state.finished[self.shard] {
+                return None;
+            }
+            let future = match self.shared.mode {
+                SplitMode::RoundRobin => state.next_round_robin(self.shard),
+                SplitMode::NextFree => state.pull(),
+            };
+            if future.is_none() {
+                state.finished[self.shard] = true;
+            }
+            let future = future?;
+
+            let id = state.next_id;
+            state.next_id += 1;
+            state.handed_out.insert(id, future);
+            Some(id)
+        })?;
+
+        Some(SplitFuture { split: self, id })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.shared.state.with(|deluge, state| {
+            // Any of the elements can still end up with the other shards
+            let state = state.lock().unwrap();
+            if state.finished[self.shard] {
+                return (0, Some(0));
+            }
+            let queued = state.queues[self.shard].len() + state.orphans.len();
+            let upper = if state.exhausted {
+                Some(0)
+            } else {
+                deluge.size_hint().1
+            };
+
+            (0, upper.and_then(|upper| upper.checked_add(queued)))
+        })
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.shared.state.with(|deluge, state| {
+            let state = state.lock().unwrap();
+            let queued = match self.shared.mode {
+                SplitMode::RoundRobin => state.queues[self.shard].len() + state.orphans.len(),
+                SplitMode::NextFree => 0,
+            };
+            if queued > 0 || state.exhausted || state.finished[self.shard] {
+                Poll::Ready(())
+            } else {
+                deluge.poll_ready(cx)
+            }
+        })
+    }
+}
+
+impl<'a, Del: Deluge + 'a> Drop for Split<'a, Del> {
+    fn drop(&mut self) {
+        self.shared.state.with(|_, state| {
+            if let Ok(mut state) = state.lock() {
+                // The elements meant for this shard go to the remaining ones,
+                // they're dropped unevaluated if all of those have finished already
+                state.live[self.shard] = false;
+                let queued = std::mem::take(&mut state.queues[self.shard]);
+                state.orphans.extend(queued);
+                if state.turn == self.shard {
+                    state.next_turn();
+                }
+            }
+        });
+    }
+}
+
+pub struct SplitFuture<'x, 'a, Del: Deluge + 'a> {
+    split: &'x Split<'a, Del>,
+    // The key of the element in `handed_out`
+    id: usize,
+}
+
+impl<'x, 'a, Del: Deluge + 'a> Future for SplitFuture<'x, 'a, Del> {
+    type Output = Option<Del::Item>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let id = self.id;
+        self.split.shared.state.with(|_, state| {
+            // The element is polled outside of the lock, so the other shards can go on meanwhile
+            let mut future = state
+                .lock()
+                .unwrap()
+                .handed_out
+                .remove(&id)
+                .expect("a split element polled after it completed");
+
+            let result = future.as_mut().poll(cx);
+            if result.is_pending() {
+                state.lock().unwrap().handed_out.insert(id, future);
+            }
+            result
+        })
+    }
+}
+
+impl<'x, 'a, Del: Deluge + 'a> Drop for SplitFuture<'x, 'a, Del> {
+    fn drop(&mut self) {
+        let id = self.id;
+        self.split.shared.state.with(|_, state| {
+            // Dropped outside of the lock, just like it's polled outside of it
+            let future = state
+                .lock()
+                .ok()
+                .and_then(|mut state| state.handed_out.remove(&id));
+            drop(future);
+        });
+    }
+}
@@ -0,0 +1,273 @@
+use crate::deluge::Deluge;
+use crate::helpers::output_slots::OutputSlots;
+use crate::helpers::owned_deluge::{Borrowers, OwnedDeluge};
+use crate::ops::catch_unwind::PanicPayload;
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// One of the shards returned by `split_by_key`
+pub struct SplitByKey<'a, Del: Deluge + 'a, KF> {
+    shard: usize,
+    shared: Arc<Shared<'a, Del, KF>>,
+}
+
+struct Shared<'a, Del: Deluge + 'a, KF> {
+    key_fn: KF,
+    state: OwnedDeluge<'a, Del, State>,
+}
+
+// The futures being evaluated borrow the deluge
+struct State;
+
+impl<'x, Del: Deluge> Borrowers<'x, Del> for State {
+    type Of = Mutex<SplitState<'x, Del>>;
+}
+
+struct SplitState<'a, Del: Deluge + 'a> {
+    deluge: &'a Del,
+    exhausted: bool,
+    // The key is only known once an element is evaluated,
+    // so the elements are evaluated here and handed to their shard afterwards
+    in_flight: Vec<Pin<Box<Del::Output<'a>>>>,
+    // A panicked element has no key, so it goes to the shard that was evaluating it
+    // and is resumed by the future taking it
+    shards: Vec<OutputSlots<Result<Del::Item, PanicPayload>>>,
+    live: Vec<bool>,
+}
+
+impl<'a, Del: Deluge + 'a, KF> SplitByKey<'a, Del, KF> {
+    pub(crate) fn new(deluge: Del, shards: usize, key_fn: KF) -> Vec<Self> {
+        let shared = Arc::new(Shared {
+            key_fn,
+            state: OwnedDeluge::new(deluge, |deluge| {
+                Mutex::new(SplitState {
+                    deluge,
+                    exhausted: false,
+                    in_flight: Vec::new(),
+                    shards: (0..shards).map(|_| OutputSlots::new()).collect(),
+                    live: vec![true; shards],
+                })
+            }),
+        });
+
+        (0..shards)
+            .map(|shard| SplitByKey {
+                shard,
+                shared: shared.clone(),
+            })
+            .collect()
+    }
+}
+
+impl<'a, Del: Deluge + 'a> SplitState<'a, Del> {
+    fn pull(&mut self) -> bool {
+        if self.exhausted {
+            return false;
+        }
+
+        match self.deluge.next() {
+            Some(future) => {
+                self.in_flight.push(Box::pin(future));
+                true
+            }
+            None => {
+                self.exhausted = true;
+                false
+            }
+        }
+    }
+
+    /// Evaluates elements until the element at `index` of the `shard` is known
+    fn advance<K, KF>(&mut self, shard: usize, index: usize, key_fn: &KF, cx: &mut Context<'_>)
+    where
+        K: Hash,
+        KF: Fn(&Del::Item) -> K,
+    {
+        let slots = &self.shards[shard];
+        if slots.produced() > index || slots.is_finished() {
+            return;
+        }
+
+        loop {
+            // Only evaluate as many elements at once as the shards are waiting for
+            let demand: usize = self
+                .shards
+                .iter()
+                .map(|slots| slots.issued().saturating_sub(slots.produced()))
+                .sum();
+            while self.in_flight.len() < demand && self.pull() {}
+
+            let mut resolved = Vec::new();
+            self.in_flight.retain_mut(|future| {
+                // A panic unwinding past the lock would poison it for the other shards
+                match std::panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                    Ok(Poll::Ready(item)) => {
+                        resolved.push(Ok(item));
+                        false
+                    }
+                    Ok(Poll::Pending) => true,
+                    Err(payload) => {
+                        resolved.push(Err(payload));
+                        false
+                    }
+                }
+            });
+            let resolved_count = resolved.len();
+
+            for item in resolved {
+                let item = match item {
+                    Ok(Some(item)) => item,
+                    Ok(None) => continue,
+                    Err(payload) => {
+                        self.shards[shard].push(Err(payload));
+                        continue;
+                    }
+                };
+
+                let mut hasher = DefaultHasher::new();
+                key_fn(&item).hash(&mut hasher);
+                let target = (hasher.finish() % self.shards.len() as u64) as usize;
+
+                // A dropped shard won't take the elements with its keys
+                if self.live[target] {
+                    self.shards[target].push(Ok(item));
+                }
+            }
+
+            if self.exhausted && self.in_flight.is_empty() {
+                self.shards.iter_mut().for_each(OutputSlots::finish);
+                return;
+            }
+            // Everything left is still being evaluated and knows to wake us up
+            if resolved_count == 0 || self.shards[shard].produced() > index {
+                return;
+            }
+        }
+    }
+}
+
+impl<'a, Del, K, KF> Deluge for SplitByKey<'a, Del, KF>
+where
+    Del: Deluge + 'a,
+    K: Hash,
+    KF: Fn(&Del::Item) -> K,
+{
+    type Item = Del::Item;
+    type Output<'x>
+        = SplitByKeyFuture<'x, 'a, Del, KF>
+    where
+        Self: 'x;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        let index = self.shared.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+
+            // Each element being evaluated might still go to this shard.
+            // Make sure there is one more than the futures handed out before handing out another.
+            let slots = &state.shards[self.shard];
+            let (issued, candidates) = (slots.issued(), slots.produced() + state.in_flight.len());
+            if issued >= candidates && !state.pull() {
+                return None;
+            }
+
+            Some(state.shards[self.shard].issue())
+        })?;
+
+        Some(SplitByKeyFuture { split: self, index })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.shared.state.with(|deluge, state| {
+            let state = state.lock().unwrap();
+            let slots = &state.shards[self.shard];
+            let upper = if state.exhausted {
+                Some(0)
+            } else {
+                deluge.size_hint().1
+            };
+            let candidates = slots.produced() + state.in_flight.len();
+
+            (
+                0,
+                upper
+                    .and_then(|upper| upper.checked_add(candidates.saturating_sub(slots.issued()))),
+            )
+        })
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.shared.state.with(|deluge, state| {
+            let state = state.lock().unwrap();
+            let slots = &state.shards[self.shard];
+            if state.exhausted || slots.issued() < slots.produced() + state.in_flight.len() {
+                Poll::Ready(())
+            } else {
+                deluge.poll_ready(cx)
+            }
+        })
+    }
+}
+
+impl<'a, Del: Deluge + 'a, KF> Drop for SplitByKey<'a, Del, KF> {
+    fn drop(&mut self) {
+        self.shared.state.with(|_, state| {
+            if let Ok(mut state) = state.lock() {
+                state.live[self.shard] = false;
+                state.shards[self.shard] = OutputSlots::new();
+            }
+        });
+    }
+}
+
+pub struct SplitByKeyFuture<'x, 'a, Del: Deluge + 'a, KF> {
+    split: &'x SplitByKey<'a, Del, KF>,
+    index: usize,
+}
+
+impl<'x, 'a, Del, K, KF> Future for SplitByKeyFuture<'x, 'a, Del, KF>
+where
+    Del: Deluge + 'a,
+    K: Hash,
+    KF: Fn(&Del::Item) -> K,
+{
+    type Output = Option<Del::Item>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (shard, index, shared) = (self.split.shard, self.index, &self.split.shared);
+        let item = shared.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+            state.advance(shard, index, &shared.key_fn, cx);
+
+            // The elements evaluated while driving might belong to other shards,
+            // in which case this one resolves to `None` once they run out
+            state.shards[shard].poll_take(index, cx)
+        });
+
+        match item {
+            Poll::Ready(Some(Err(payload))) => std::panic::resume_unwind(payload),
+            Poll::Ready(Some(Ok(item))) => Poll::Ready(Some(item)),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<'x, 'a, Del: Deluge + 'a, KF> Drop for SplitByKeyFuture<'x, 'a, Del, KF> {
+    fn drop(&mut self) {
+        let (shard, index) = (self.split.shard, self.index);
+        self.split.shared.state.with(|_, state| {
+            if let Ok(mut state) = state.lock() {
+                // The elements being evaluated might only know to wake this future up,
+                // so a future of any shard has to take over driving them
+                if !state.shards[shard].release(index) {
+                    state.shards.iter().any(OutputSlots::wake_one);
+                }
+            }
+        });
+    }
+}
@@ -1,6 +1,6 @@
 use crate::deluge::Deluge;
-use futures::Future;
 use std::cell::RefCell;
+use std::task::{Context, Poll};
 
 pub struct Take<Del> {
     deluge: Del,
@@ -22,15 +22,44 @@ where
     Del: Deluge + 'static,
 {
     type Item = Del::Item;
-    type Output<'a> = impl Future<Output = Option<Self::Item>> + 'a;
+    type Output<'a> = Del::Output<'a>;
 
     fn next(&self) -> Option<Self::Output<'_>> {
         let mut how_many_provided = self.how_many_provided.borrow_mut();
         if *how_many_provided < self.how_many {
-            *how_many_provided += 1;
-            self.deluge.next()
+            let next = self.deluge.next();
+            // Don't go back to an exhausted deluge, even if fewer elements were taken
+            *how_many_provided = if next.is_some() {
+                *how_many_provided + 1
+            } else {
+                self.how_many
+            };
+            next
         } else {
             None
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.how_many - *self.how_many_provided.borrow();
+        let (lower, upper) = self.deluge.size_hint();
+        // The filtered out elements might all be among the ones taken
+        let exact = Some(lower) == upper;
+        (
+            if exact { lower.min(remaining) } else { 0 },
+            Some(upper.map_or(remaining, |upper| upper.min(remaining))),
+        )
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if *self.how_many_provided.borrow() < self.how_many {
+            self.deluge.poll_ready(cx)
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
 }
@@ -0,0 +1,301 @@
+use crate::deluge::Deluge;
+use crate::helpers::owned_deluge::{Borrowers, OwnedDeluge};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// One of the deluges returned by `tee`
+pub struct Tee<'a, Del: Deluge + 'a> {
+    branch: usize,
+    shared: Arc<Shared<'a, Del>>,
+}
+
+struct Shared<'a, Del: Deluge + 'a> {
+    buffer: usize,
+    state: OwnedDeluge<'a, Del, Slots>,
+}
+
+// The elements being evaluated borrow the deluge
+struct Slots;
+
+impl<'x, Del: Deluge> Borrowers<'x, Del> for Slots {
+    type Of = Mutex<TeeState<'x, Del>>;
+}
+
+struct TeeState<'a, Del: Deluge + 'a> {
+    exhausted: bool,
+    // Index of the first element in `slots`
+    base: usize,
+    slots: VecDeque<Slot<'a, Del>>,
+    // Index of the next element each branch is going to return
+    positions: Vec<usize>,
+    live_branches: usize,
+}
+
+struct Slot<'a, Del: Deluge + 'a> {
+    element: Element<'a, Del>,
+    // How many branches are yet to take the element
+    remaining: usize,
+    waiting: Vec<Option<Waker>>,
+}
+
+enum Element<'a, Del: Deluge + 'a> {
+    Evaluating(Pin<Box<Del::Output<'a>>>),
+    Resolved(Option<Del::Item>),
+}
+
+impl<'a, Del: Deluge + 'a> Tee<'a, Del> {
+    pub(crate) fn new(deluge: Del, branches: usize, buffer: usize) -> Vec<Self> {
+        let shared = Arc::new(Shared {
+            buffer: buffer.max(1),
+            state: OwnedDeluge::new(deluge, |_| {
+                Mutex::new(TeeState {
+                    exhausted: false,
+                    base: 0,
+                    slots: VecDeque::new(),
+                    positions: vec![0; branches],
+                    live_branches: branches,
+                })
+            }),
+        });
+
+        (0..branches)
+            .map(|branch| Tee {
+                branch,
+                shared: shared.clone(),
+            })
+            .collect()
+    }
+}
+
+impl<'a, Del: Deluge + 'a> TeeState<'a, Del> {
+    fn slot_mut(&mut self, index: usize) -> &mut Slot<'a, Del> {
+        let base = self.base;
+        &mut self.slots[index - base]
+    }
+
+    /// Marks the element as taken by one more branch,
+    /// making room for the following ones once every branch took it
+    fn release(&mut self, index: usize, buffer: usize) {
+        self.slot_mut(index).remaining -= 1;
+
+        let window_before = self.base + buffer;
+        while self.slots.front().is_some_and(|slot| slot.remaining == 0) {
+            self.slots.pop_front();
+            self.base += 1;
+        }
+
+        // Wake up the elements that were waiting for room to be evaluated
+        let (base, window) = (self.base, self.base + buffer);
+        for index in window_before.max(base)..window.min(base + self.slots.len()) {
+            self.slot_mut(index).wake_all();
+        }
+    }
+}
+
+impl<'a, Del: Deluge + 'a> Slot<'a, Del> {
+    fn wake_all(&mut self) {
+        self.waiting
+            .iter_mut()
+            .filter_map(Option::take)
+            .for_each(Waker::wake);
+    }
+}
+
+impl<'a, Del> Deluge for Tee<'a, Del>
+where
+    Del: Deluge + 'a,
+    Del::Item: Clone,
+{
+    type Item = Del::Item;
+    type Output<'x>
+        = TeeFuture<'x, 'a, Del>
+    where
+        Self: 'x;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.shared.state.with(|deluge, state| {
+            let mut state = state.lock().unwrap();
+            let index = state.positions[self.branch];
+
+            // The first branch to get this far pulls the element for everyone,
+            // it is evaluated once the slowest branch leaves room for it
+            if index == state.base + state.slots.len() {
+                if state.exhausted {
+                    return None;
+                }
+                match deluge.next() {
+                    Some(future) => {
+                        let slot = Slot {
+                            element: Element::Evaluating(Box::pin(future)),
+                            remaining: state.live_branches,
+                            waiting: (0..state.positions.len()).map(|_| None).collect(),
+                        };
+                        state.slots.push_back(slot);
+                    }
+                    None => {
+                        state.exhausted = true;
+                        return None;
+                    }
+                }
+            }
+
+            state.positions[self.branch] += 1;
+            Some(TeeFuture {
+                tee: self,
+                index,
+                done: false,
+            })
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.shared.state.with(|deluge, state| {
+            let state = state.lock().unwrap();
+            let position = state.positions[self.branch];
+            let pulled = state.base + state.slots.len() - position;
+            let resolved = state
+                .slots
+                .range(position - state.base..)
+                .filter(|slot| matches!(slot.element, Element::Resolved(Some(_))))
+                .count();
+
+            if state.exhausted {
+                (resolved, Some(pulled))
+            } else {
+                let (lower, upper) = deluge.size_hint();
+                (
+                    lower.saturating_add(resolved),
+                    upper.and_then(|upper| upper.checked_add(pulled)),
+                )
+            }
+        })
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.shared.state.with(|deluge, state| {
+            let state = state.lock().unwrap();
+            // Only the first branch to get this far asks the deluge
+            let pulled = state.positions[self.branch] < state.base + state.slots.len();
+            if pulled || state.exhausted {
+                Poll::Ready(())
+            } else {
+                deluge.poll_ready(cx)
+            }
+        })
+    }
+}
+
+impl<'a, Del: Deluge + 'a> Drop for Tee<'a, Del> {
+    fn drop(&mut self) {
+        self.shared.state.with(|_, state| {
+            if let Ok(mut state) = state.lock() {
+                // The elements this branch didn't get to don't have to wait for it
+                state.live_branches -= 1;
+                let position = state.positions[self.branch];
+                let end = state.base + state.slots.len();
+                for index in position..end {
+                    state.release(index, self.shared.buffer);
+                }
+                state.positions[self.branch] = end;
+            }
+        });
+    }
+}
+
+pub struct TeeFuture<'x, 'a, Del: Deluge + 'a> {
+    tee: &'x Tee<'a, Del>,
+    index: usize,
+    done: bool,
+}
+
+impl<'x, 'a, Del> Future for TeeFuture<'x, 'a, Del>
+where
+    Del: Deluge + 'a,
+    Del::Item: Clone,
+{
+    type Output = Option<Del::Item>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let (branch, index, buffer) = (self.tee.branch, self.index, self.tee.shared.buffer);
+        let item = self.tee.shared.state.with(|_, state| {
+            let mut state = state.lock().unwrap();
+            let in_buffer = index < state.base + buffer;
+            let slot = state.slot_mut(index);
+
+            if let Element::Evaluating(future) = &mut slot.element {
+                let resolved = if in_buffer {
+                    // A panic unwinding past the lock would poison it for the other branches
+                    std::panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx)))
+                } else {
+                    Ok(Poll::Pending)
+                };
+                match resolved {
+                    Ok(Poll::Ready(item)) => {
+                        slot.element = Element::Resolved(item);
+                        slot.wake_all();
+                    }
+                    Ok(Poll::Pending) => {
+                        slot.waiting[branch] = Some(cx.waker().clone());
+                        return Ok(Poll::Pending);
+                    }
+                    // The other branches see the panicked element as filtered out
+                    Err(payload) => {
+                        slot.element = Element::Resolved(None);
+                        slot.wake_all();
+                        slot.waiting[branch] = None;
+                        state.release(index, buffer);
+                        return Err(payload);
+                    }
+                }
+            }
+
+            // The last branch to take the element doesn't need to clone it
+            let item = match &mut slot.element {
+                Element::Resolved(item) if slot.remaining == 1 => item.take(),
+                Element::Resolved(item) => item.clone(),
+                Element::Evaluating(_) => unreachable!(),
+            };
+            slot.waiting[branch] = None;
+            state.release(index, buffer);
+            Ok(Poll::Ready(item))
+        });
+
+        match item {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(item) => {
+                self.done = true;
+                item
+            }
+            Err(payload) => {
+                self.done = true;
+                std::panic::resume_unwind(payload)
+            }
+        }
+    }
+}
+
+impl<'x, 'a, Del: Deluge + 'a> Drop for TeeFuture<'x, 'a, Del> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        let (branch, index, buffer) = (self.tee.branch, self.index, self.tee.shared.buffer);
+        self.tee.shared.state.with(|_, state| {
+            if let Ok(mut state) = state.lock() {
+                let slot = state.slot_mut(index);
+                slot.waiting[branch] = None;
+                // The element might only know to wake the dropped future up,
+                // so another branch waiting for it has to take over evaluating it
+                if let Some(waker) = slot.waiting.iter().flatten().next() {
+                    waker.wake_by_ref();
+                }
+                state.release(index, buffer);
+            }
+        });
+    }
+}
@@ -0,0 +1,171 @@
+use crate::deluge::Deluge;
+use crate::runtime::{DefaultRuntime, Runtime};
+use pin_project::pin_project;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Decides what happens with an element that did not evaluate in time
+/// and what the elements that did look like
+pub trait TimeoutKind<T> {
+    type Item;
+    /// Whether the elements that did not evaluate in time are left out
+    const DROPS: bool;
+
+    fn item(item: T) -> Self::Item;
+    fn elapsed() -> Option<Self::Item>;
+}
+
+/// Elements that did not evaluate in time are dropped, as if they were filtered out,
+/// the rest is left as is
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DropOnTimeout;
+
+/// Elements that did not evaluate in time become `Err(Elapsed)`,
+/// the rest becomes `Ok(element)`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ErrorOnTimeout;
+
+impl<T> TimeoutKind<T> for DropOnTimeout {
+    type Item = T;
+    const DROPS: bool = true;
+
+    fn item(item: T) -> Self::Item {
+        item
+    }
+
+    fn elapsed() -> Option<Self::Item> {
+        None
+    }
+}
+
+impl<T> TimeoutKind<T> for ErrorOnTimeout {
+    type Item = Result<T, Elapsed>;
+    const DROPS: bool = false;
+
+    fn item(item: T) -> Self::Item {
+        Ok(item)
+    }
+
+    fn elapsed() -> Option<Self::Item> {
+        Some(Err(Elapsed(())))
+    }
+}
+
+/// The error returned for elements that did not evaluate in time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elapsed(());
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "deluge element timed out")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+pub struct Timeout<Del, K, R = DefaultRuntime> {
+    deluge: Del,
+    duration: Duration,
+    runtime: R,
+    _kind: PhantomData<K>,
+}
+
+impl<Del, K> Timeout<Del, K> {
+    pub(crate) fn new(deluge: Del, duration: Duration) -> Self {
+        Self {
+            deluge,
+            duration,
+            runtime: DefaultRuntime::default(),
+            _kind: PhantomData,
+        }
+    }
+}
+
+impl<Del, K, R> Timeout<Del, K, R> {
+    /// Measures the elapsed time with a different `runtime`
+    pub fn with_runtime<R2: Runtime>(self, runtime: R2) -> Timeout<Del, K, R2> {
+        Timeout {
+            deluge: self.deluge,
+            duration: self.duration,
+            runtime,
+            _kind: PhantomData,
+        }
+    }
+}
+
+impl<Del, K, R> Deluge for Timeout<Del, K, R>
+where
+    Del: Deluge,
+    K: TimeoutKind<Del::Item>,
+    R: Runtime,
+{
+    type Item = K::Item;
+    type Output<'a>
+        = TimeoutFuture<'a, Del, K, R>
+    where
+        Self: 'a;
+
+    fn next(&self) -> Option<Self::Output<'_>> {
+        self.deluge.next().map(|item| TimeoutFuture {
+            timeout: self,
+            item,
+            timer: None,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if K::DROPS {
+            (0, self.deluge.size_hint().1)
+        } else {
+            self.deluge.size_hint()
+        }
+    }
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.deluge.poll_ready(cx)
+    }
+
+    fn parked(&self) -> usize {
+        self.deluge.parked()
+    }
+}
+
+#[pin_project]
+pub struct TimeoutFuture<'a, Del: Deluge + 'a, K, R: Runtime> {
+    timeout: &'a Timeout<Del, K, R>,
+    #[pin]
+    item: Del::Output<'a>,
+    #[pin]
+    timer: Option<R::Sleep>,
+}
+
+impl<'a, Del, K, R> Future for TimeoutFuture<'a, Del, K, R>
+where
+    Del: Deluge + 'a,
+    K: TimeoutKind<Del::Item>,
+    R: Runtime,
+{
+    type Output = Option<K::Item>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        if this.timer.is_none() {
+            // The timer only starts when the element is first polled by the collector
+            let timer = this.timeout.runtime.sleep(this.timeout.duration);
+            this.timer.set(Some(timer));
+        }
+
+        if let Poll::Ready(item) = this.item.poll(cx) {
+            return Poll::Ready(item.map(K::item));
+        }
+
+        match this.timer.as_pin_mut().map(|timer| timer.poll(cx)) {
+            Some(Poll::Ready(())) => Poll::Ready(K::elapsed()),
+            _ => Poll::Pending,
+        }
+    }
+}
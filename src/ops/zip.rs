@@ -1,20 +1,18 @@
 use super::collect::Collect;
+use crate::concurrency::Concurrency;
 use crate::deluge::Deluge;
 use crate::helpers::indexable_stream::*;
 use crate::helpers::preloaded_futures::*;
 
+use futures::lock::{Mutex, MutexGuard, MutexLockFuture};
+use futures::ready;
 use pin_project::pin_project;
 use std::cell::RefCell;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
-#[cfg(feature = "tokio")]
-type Mutex<T> = tokio::sync::Mutex<T>;
-#[cfg(feature = "async-std")]
-type Mutex<T> = async_std::sync::Mutex<T>;
-
-#[pin_project]
 pub struct Zip<'a, Del1, Del2>
 where
     Del1: Deluge + 'a,
@@ -24,6 +22,8 @@ where
 
     provided_elems: RefCell<usize>,
     elems_to_provide: usize,
+    // How many of the elements are certain to be present on both sides
+    elems_present: usize,
 }
 
 struct Streams<'a, Del1, Del2>
@@ -40,23 +40,30 @@ where
     Del1: Deluge + 'a,
     Del2: Deluge + 'a,
 {
-    pub(crate) fn new(first: Del1, second: Del2, concurrency: impl Into<Option<usize>>) -> Self {
+    pub(crate) fn new(first: Del1, second: Del2, concurrency: impl Into<Concurrency>) -> Self {
         let concurrency = concurrency.into();
+        let elems_present = std::cmp::min(first.size_hint().0, second.size_hint().0);
 
         // Preload the futures from each
         let preloaded1 = PreloadedFutures::new(first);
         let preloaded2 = PreloadedFutures::new(second);
 
         let elems_to_provide = std::cmp::min(preloaded1.len(), preloaded2.len());
+        // A deluge might promise more elements than it actually had
+        let elems_present = std::cmp::min(elems_present, elems_to_provide);
 
         Self {
             streams: Mutex::new(Streams {
-                first: Arc::new(IndexableStream::new(Collect::new(preloaded1, concurrency))),
+                first: Arc::new(IndexableStream::new(Collect::new(
+                    preloaded1,
+                    concurrency.clone(),
+                ))),
                 second: Arc::new(IndexableStream::new(Collect::new(preloaded2, concurrency))),
             }),
 
             provided_elems: RefCell::new(0),
             elems_to_provide,
+            elems_present,
         }
     }
 }
@@ -67,7 +74,10 @@ where
     Del2: Deluge + 'a,
 {
     type Item = (Del1::Item, Del2::Item);
-    type Output<'x> = impl Future<Output = Option<Self::Item>> + 'x where Self: 'x;
+    type Output<'x>
+        = ZipFuture<'x, 'a, Del1, Del2>
+    where
+        Self: 'x;
 
     fn next(&self) -> Option<Self::Output<'_>> {
         let mut provided_elems = self.provided_elems.borrow_mut();
@@ -77,22 +87,79 @@ where
             let current_index = *provided_elems;
 
             *provided_elems += 1;
-            Some(async move {
-                let this = Pin::new(self).project_ref();
-
-                let (first_el, second_el) = {
-                    let streams = this.streams.lock().await;
-                    (
-                        streams.first.clone().get_nth(current_index).await,
-                        streams.second.clone().get_nth(current_index).await,
-                    )
-                };
-
-                match (first_el, second_el) {
-                    (Some(first), Some(second)) => Some((first, second)),
-                    _ => None,
-                }
+            Some(ZipFuture {
+                index: current_index,
+                streams: None,
+                state: ZipState::Locking(self.streams.lock()),
             })
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let provided_elems = *self.provided_elems.borrow();
+        (
+            self.elems_present.saturating_sub(provided_elems),
+            Some(self.elems_to_provide - provided_elems),
+        )
+    }
+}
+
+#[pin_project]
+pub struct ZipFuture<'x, 'a, Del1, Del2>
+where
+    Del1: Deluge + 'a,
+    Del2: Deluge + 'a,
+{
+    index: usize,
+    // Both elements are fetched while holding the lock
+    streams: Option<MutexGuard<'x, Streams<'a, Del1, Del2>>>,
+    state: ZipState<'x, 'a, Del1, Del2>,
+}
+
+enum ZipState<'x, 'a, Del1, Del2>
+where
+    Del1: Deluge + 'a,
+    Del2: Deluge + 'a,
+{
+    Locking(MutexLockFuture<'x, Streams<'a, Del1, Del2>>),
+    First(GetNthElement<'a, Collect<'a, PreloadedFutures<'a, Del1>, ()>>),
+    Second(
+        Option<Del1::Item>,
+        GetNthElement<'a, Collect<'a, PreloadedFutures<'a, Del2>, ()>>,
+    ),
+}
+
+impl<'x, 'a, Del1, Del2> Future for ZipFuture<'x, 'a, Del1, Del2>
+where
+    Del1: Deluge + 'a,
+    Del2: Deluge + 'a,
+{
+    type Output = Option<(Del1::Item, Del2::Item)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        loop {
+            match this.state {
+                ZipState::Locking(lock) => {
+                    let streams = this.streams.insert(ready!(Pin::new(lock).poll(cx)));
+                    *this.state = ZipState::First(streams.first.clone().get_nth(*this.index));
+                }
+                ZipState::First(first) => {
+                    let first_el = ready!(Pin::new(first).poll(cx));
+                    let streams = this.streams.as_ref().unwrap();
+                    *this.state =
+                        ZipState::Second(first_el, streams.second.clone().get_nth(*this.index));
+                }
+                ZipState::Second(first_el, second) => {
+                    let second_el = ready!(Pin::new(second).poll(cx));
+                    this.streams.take();
+
+                    return Poll::Ready(match (first_el.take(), second_el) {
+                        (Some(first), Some(second)) => Some((first, second)),
+                        _ => None,
+                    });
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,251 @@
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+
+/// The pieces of an async runtime that the time based operations and `map_blocking` rely on.
+///
+/// Implementations for tokio, async-std and smol are provided behind their features,
+/// but any other runtime can be plugged in by implementing this trait
+/// and passing it to an operation with `with_runtime`.
+///
+/// The parallel collectors and `zip` don't need a runtime, so they don't take one
+/// and are built without any of the runtime features.
+/// Their workers are futures polled by the collector itself rather than spawned tasks,
+/// and the channels and mutexes shared between them come from the `futures` crate,
+/// which work on any executor. That's also why spawning, channels and mutexes
+/// are left out of this trait.
+///
+/// # Examples
+///
+/// ```
+/// use deluge::*;
+/// use futures::future::BoxFuture;
+/// use std::time::Duration;
+///
+/// #[derive(Clone, Copy, Debug, Default)]
+/// struct ThreadRuntime;
+///
+/// impl Runtime for ThreadRuntime {
+///     type Sleep = BoxFuture<'static, ()>;
+///
+///     fn sleep(&self, duration: Duration) -> Self::Sleep {
+///         let (sender, receiver) = futures::channel::oneshot::channel();
+///         std::thread::spawn(move || {
+///             std::thread::sleep(duration);
+///             sender.send(()).ok();
+///         });
+///         Box::pin(async move {
+///             receiver.await.ok();
+///         })
+///     }
+/// }
+///
+/// # #[cfg(feature = "async-runtime")]
+/// # futures::executor::block_on(async {
+/// let result = [1, 2, 3]
+///     .into_deluge()
+///     .rate_limit(1, Duration::from_millis(10), None)
+///     .with_runtime(ThreadRuntime)
+///     .collect::<Vec<usize>>(None)
+///     .await;
+///
+/// assert_eq!(result, vec![1, 2, 3]);
+/// # });
+/// ```
+pub trait Runtime: Clone + Send + Sync + 'static {
+    type Sleep: Future<Output = ()> + Send + 'static;
+
+    /// Resolves after `duration` has passed
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+
+    /// Runs the blocking closure `f` away from the async executor, resolving to its result.
+    /// A panic of `f` is resumed when the result is awaited.
+    ///
+    /// Starts a new thread for every call unless the runtime provides a thread pool for it.
+    fn spawn_blocking<F, T>(&self, f: F) -> BoxFuture<'static, T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        spawn_blocking_on(
+            |job| {
+                std::thread::spawn(job);
+            },
+            f,
+        )
+    }
+}
+
+/// Hands `f` over to `run`, which executes it on some other thread,
+/// and resolves to its result, resuming its panic if there was one
+fn spawn_blocking_on<F, T>(
+    run: impl FnOnce(Box<dyn FnOnce() + Send + 'static>),
+    f: F,
+) -> BoxFuture<'static, T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    run(Box::new(move || {
+        sender
+            .send(std::panic::catch_unwind(AssertUnwindSafe(f)))
+            .ok();
+    }));
+
+    Box::pin(async move {
+        match receiver.await.expect("the blocking task was dropped") {
+            Ok(output) => output,
+            Err(payload) => std::panic::resume_unwind(payload),
+        }
+    })
+}
+
+/// Runs the operations on the tokio runtime the caller is running on
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "tokio")]
+impl Runtime for TokioRuntime {
+    type Sleep = tokio::time::Sleep;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        tokio::time::sleep(duration)
+    }
+
+    fn spawn_blocking<F, T>(&self, f: F) -> BoxFuture<'static, T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let handle = tokio::task::spawn_blocking(f);
+        Box::pin(async move {
+            match handle.await {
+                Ok(output) => output,
+                Err(e) => std::panic::resume_unwind(e.into_panic()),
+            }
+        })
+    }
+}
+
+/// Runs the operations on the global async-std executor
+#[cfg(feature = "async-std")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "async-std")]
+impl Runtime for AsyncStdRuntime {
+    type Sleep = BoxFuture<'static, ()>;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        Box::pin(async_std::task::sleep(duration))
+    }
+
+    fn spawn_blocking<F, T>(&self, f: F) -> BoxFuture<'static, T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        Box::pin(async_std::task::spawn_blocking(f))
+    }
+}
+
+/// Runs the operations on the global smol executor
+#[cfg(feature = "smol")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol")]
+impl Runtime for SmolRuntime {
+    type Sleep = BoxFuture<'static, ()>;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        let timer = smol::Timer::after(duration);
+        Box::pin(async move {
+            timer.await;
+        })
+    }
+
+    fn spawn_blocking<F, T>(&self, f: F) -> BoxFuture<'static, T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        Box::pin(smol::unblock(f))
+    }
+}
+
+/// Runs the blocking closures on the global rayon thread pool,
+/// leaving the timers to the `inner` runtime
+///
+/// # Examples
+///
+/// ```
+/// use deluge::*;
+///
+/// # let rt = tokio::runtime::Runtime::new().unwrap();
+/// # rt.handle().block_on(async {
+/// let result = [1, 2, 3]
+///     .into_deluge()
+///     .map_blocking(|x| x * 2)
+///     .with_runtime(RayonRuntime::default())
+///     .collect::<Vec<usize>>(None)
+///     .await;
+///
+/// assert_eq!(result, vec![2, 4, 6]);
+/// # });
+/// ```
+#[cfg(all(feature = "rayon", feature = "async-runtime"))]
+#[derive(Clone, Copy, Debug)]
+pub struct RayonRuntime<R = DefaultRuntime> {
+    inner: R,
+}
+
+#[cfg(all(feature = "rayon", feature = "async-runtime"))]
+impl Default for RayonRuntime {
+    fn default() -> Self {
+        Self::new(DefaultRuntime::default())
+    }
+}
+
+#[cfg(all(feature = "rayon", feature = "async-runtime"))]
+impl<R: Runtime> RayonRuntime<R> {
+    /// Uses rayon for the blocking closures and `inner` for everything else
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+#[cfg(all(feature = "rayon", feature = "async-runtime"))]
+impl<R: Runtime> Runtime for RayonRuntime<R> {
+    type Sleep = R::Sleep;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        self.inner.sleep(duration)
+    }
+
+    fn spawn_blocking<F, T>(&self, f: F) -> BoxFuture<'static, T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        spawn_blocking_on(rayon::spawn, f)
+    }
+}
+
+/// The runtime used when none is given explicitly.
+/// Tokio if its feature is enabled, then async-std and smol.
+#[cfg(feature = "tokio")]
+pub type DefaultRuntime = TokioRuntime;
+
+/// The runtime used when none is given explicitly.
+/// Tokio if its feature is enabled, then async-std and smol.
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+pub type DefaultRuntime = AsyncStdRuntime;
+
+/// The runtime used when none is given explicitly.
+/// Tokio if its feature is enabled, then async-std and smol.
+#[cfg(all(feature = "smol", not(any(feature = "tokio", feature = "async-std"))))]
+pub type DefaultRuntime = SmolRuntime;